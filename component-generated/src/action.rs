@@ -1,15 +1,84 @@
 use serde::{Deserialize, Serialize};
-use strum::Display;
+use strum::{Display, EnumIter, EnumMessage, IntoEnumIterator, VariantNames};
 
-#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+use crate::app::Mode;
+
+/// Actions that components and keybindings send to the application.
+///
+/// The `message` of each variant is the description shown in the help popup. It can be
+/// overridden with the `descriptions` section of the configuration.
+///
+/// The default action only fills in the arguments of [`Action::Confirm`] when iterating over the
+/// variants.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    Display,
+    EnumIter,
+    EnumMessage,
+    VariantNames,
+    Serialize,
+    Deserialize,
+)]
 pub enum Action {
+    #[default]
+    #[strum(message = "Advance the application by one tick")]
     Tick,
+    #[strum(message = "Draw the screen")]
     Render,
+    #[strum(message = "Resize the screen")]
     Resize(u16, u16),
+    #[strum(message = "Suspend the application")]
     Suspend,
+    #[strum(message = "Resume the application after suspending")]
     Resume,
+    #[strum(message = "Quit the application")]
     Quit,
+    #[strum(message = "Clear and redraw the screen")]
     ClearScreen,
+    #[strum(message = "Show an error message")]
     Error(String),
+    #[strum(message = "Print a line above an inline viewport")]
+    Print(String),
+    #[strum(message = "Show the keybindings of the current mode")]
     Help,
+    #[strum(message = "Focus the next component")]
+    FocusNext,
+    #[strum(message = "Focus the previous component")]
+    FocusPrevious,
+    #[strum(message = "Open a screen on top of the current one")]
+    PushScreen(Mode),
+    #[strum(message = "Close the current screen")]
+    PopScreen,
+    #[strum(message = "Replace the current screen")]
+    ReplaceScreen(Mode),
+    #[strum(message = "Ask for confirmation before running an action")]
+    Confirm(String, Box<Action>),
+    #[strum(message = "Close the topmost popup")]
+    CloseOverlay,
+    #[strum(message = "Search and run any action")]
+    CommandPalette,
+    #[strum(message = "Reload the configuration files")]
+    ReloadConfig,
+    #[strum(message = "Switch to another theme")]
+    SetTheme(String),
+}
+
+impl Action {
+    /// The built-in description of the action.
+    pub fn description(&self) -> Option<&'static str> {
+        self.get_message()
+    }
+
+    /// The built-in description of every variant by name, also of the variants that take
+    /// arguments, which cannot be built from their name alone.
+    pub fn descriptions() -> impl Iterator<Item = (&'static str, Option<&'static str>)> {
+        Self::VARIANTS
+            .iter()
+            .copied()
+            .zip(Self::iter().map(|action| action.get_message()))
+    }
 }
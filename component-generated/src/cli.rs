@@ -1,17 +1,102 @@
-use clap::Parser;
+use std::path::PathBuf;
 
-use crate::config::{get_config_dir, get_data_dir};
+use clap::{Parser, Subcommand, ValueEnum};
+use ratatui::Viewport;
+
+use crate::{
+    color_depth::ColorDepth,
+    config::{get_config_dir, get_data_dir, ConfigSources},
+    tui::parse_viewport,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version = version(), about)]
 pub struct Cli {
-    /// Tick rate, i.e. number of ticks per second
-    #[arg(short, long, value_name = "FLOAT", default_value_t = 4.0)]
-    pub tick_rate: f64,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Tick rate, i.e. number of ticks per second [default: 4]
+    #[arg(short, long, value_name = "FLOAT")]
+    pub tick_rate: Option<f64>,
+
+    /// Frame rate, i.e. the maximum number of frames per second [default: 60]
+    #[arg(short, long, value_name = "FLOAT")]
+    pub frame_rate: Option<f64>,
+
+    /// Read this configuration file instead of the ones in the config directories
+    #[arg(short, long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Skip invalid configuration entries instead of failing
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Colors the terminal can display, detected from `COLORTERM`, `TERM` and `NO_COLOR` if not set
+    #[arg(long, value_name = "DEPTH")]
+    pub color_depth: Option<ColorDepth>,
+
+    /// Where to draw: `fullscreen`, `inline:<HEIGHT>` lines below the prompt, or
+    /// `fixed:<X>,<Y>,<WIDTH>,<HEIGHT>`
+    #[arg(long, value_name = "VIEWPORT", value_parser = parse_viewport, default_value = "fullscreen")]
+    pub viewport: Viewport,
+
+    /// Write every event to this file as JSON lines, to replay the session later
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Replay the events recorded with `--record` before reading input from the terminal
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Where the configuration is read from. The flags override the configuration files and
+    /// environment variables.
+    pub fn config_sources(&self) -> ConfigSources {
+        ConfigSources {
+            file: self.config.clone(),
+            tick_rate: self.tick_rate,
+            frame_rate: self.frame_rate,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Manage the configuration files
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write the default configuration to the config directory
+    Init {
+        /// Replace the configuration files that already exist, which are renamed to `<file>.bak`
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the config and data directories and the configuration files that are read
+    Path,
+    /// Report every problem with the configuration, and exit with an error if there are any
+    Check {
+        /// A file to check instead of the files in the config directory
+        path: Option<PathBuf>,
+    },
+    /// Print the configuration in effect, merged from the defaults and the configuration files
+    Show {
+        #[arg(short, long, value_enum, default_value_t = ConfigFormat::Json5)]
+        format: ConfigFormat,
+    },
+}
 
-    /// Frame rate, i.e. number of frames per second
-    #[arg(short, long, value_name = "FLOAT", default_value_t = 60.0)]
-    pub frame_rate: f64,
+/// The formats that `config show` can print the configuration in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json5,
+    Yaml,
+    Toml,
 }
 
 const VERSION_MESSAGE: &str = concat!(
@@ -0,0 +1,240 @@
+use ratatui::{buffer::Buffer, style::Color};
+
+/// The 16 ANSI colors, in the order of their indexes.
+const ANSI_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// The RGB values of the 16 ANSI colors in xterm's default palette. Terminals differ here, but
+/// this is close enough to find the nearest color.
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each component in the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors the terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum ColorDepth {
+    /// No colors, only modifiers such as bold
+    #[value(name = "none")]
+    NoColor,
+    /// The 16 ANSI colors
+    #[value(name = "16")]
+    Ansi16,
+    /// The 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// 24-bit RGB colors
+    #[value(name = "truecolor")]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detects the color depth from the `NO_COLOR`, `COLORTERM` and `TERM` environment variables.
+    pub fn detect() -> Self {
+        Self::from_env(
+            std::env::var("NO_COLOR").ok().as_deref(),
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        // https://no-color.org: any non-empty value disables colors
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return Self::NoColor;
+        }
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        match term {
+            // Windows terminals do not set `TERM`, and support RGB colors since Windows 10
+            None if cfg!(windows) => Self::TrueColor,
+            None | Some("dumb") => Self::NoColor,
+            Some(term) if term.ends_with("-direct") || term.contains("truecolor") => {
+                Self::TrueColor
+            }
+            Some(term) if term.contains("256color") => Self::Ansi256,
+            Some(_) => Self::Ansi16,
+        }
+    }
+
+    /// The nearest color to `color` that can be displayed with this color depth.
+    pub fn downsample(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::TrueColor, color) | (_, color @ Color::Reset) => color,
+            (Self::NoColor, _) => Color::Reset,
+            (Self::Ansi256, Color::Rgb(red, green, blue)) => {
+                Color::Indexed(nearest_indexed(red, green, blue))
+            }
+            (Self::Ansi256, color) => color,
+            (Self::Ansi16, Color::Indexed(index)) if index < 16 => ANSI_COLORS[index as usize],
+            (Self::Ansi16, Color::Indexed(index)) => {
+                let (red, green, blue) = indexed_to_rgb(index);
+                nearest_ansi(red, green, blue)
+            }
+            (Self::Ansi16, Color::Rgb(red, green, blue)) => nearest_ansi(red, green, blue),
+            (Self::Ansi16, color) => color,
+        }
+    }
+
+    /// Replaces every color in `buffer` with the nearest color that can be displayed.
+    pub fn downsample_buffer(self, buffer: &mut Buffer) {
+        if self == Self::TrueColor {
+            return;
+        }
+        for cell in buffer.content.iter_mut() {
+            cell.fg = self.downsample(cell.fg);
+            cell.bg = self.downsample(cell.bg);
+            cell.underline_color = self.downsample(cell.underline_color);
+        }
+    }
+}
+
+/// The nearest of the 16 ANSI colors.
+fn nearest_ansi(red: u8, green: u8, blue: u8) -> Color {
+    let nearest = (0..ANSI_RGB.len())
+        .min_by_key(|index| distance(ANSI_RGB[*index], (red, green, blue)))
+        .unwrap_or_default();
+    ANSI_COLORS[nearest]
+}
+
+/// The index of the nearest color in the color cube or the grayscale ramp of the 256-color
+/// palette. The 16 ANSI colors are left out, because terminals often change them.
+fn nearest_indexed(red: u8, green: u8, blue: u8) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|index| CUBE_LEVELS[*index].abs_diff(value))
+            .unwrap_or_default() as u8
+    };
+    let cube = 16 + 36 * level(red) + 6 * level(green) + level(blue);
+    let average = (red as u16 + green as u16 + blue as u16) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    let color = (red, green, blue);
+    if distance(indexed_to_rgb(gray), color) < distance(indexed_to_rgb(cube), color) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// The RGB value of a color of the 256-color palette.
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_RGB[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let square = |a: u8, b: u8| (a.abs_diff(b) as u32).pow(2);
+    square(a.0, b.0) + square(a.1, b.1) + square(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use ratatui::{layout::Rect, style::Style};
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        use ColorDepth::*;
+        let detect = ColorDepth::from_env;
+        assert_eq!(
+            detect(None, Some("truecolor"), Some("xterm-256color")),
+            TrueColor
+        );
+        assert_eq!(detect(None, Some("24bit"), None), TrueColor);
+        assert_eq!(detect(None, None, Some("xterm-direct")), TrueColor);
+        assert_eq!(detect(None, None, Some("tmux-256color")), Ansi256);
+        assert_eq!(detect(None, None, Some("linux")), Ansi16);
+        assert_eq!(detect(None, None, Some("dumb")), NoColor);
+        assert_eq!(detect(Some("1"), Some("truecolor"), None), NoColor);
+        assert_eq!(detect(Some(""), None, Some("screen")), Ansi16);
+    }
+
+    #[test]
+    fn test_downsample_to_256_colors() {
+        let depth = ColorDepth::Ansi256;
+        assert_eq!(depth.downsample(Color::Rgb(255, 0, 0)), Color::Indexed(196));
+        assert_eq!(
+            depth.downsample(Color::Rgb(0x1e, 0x1e, 0x2e)),
+            Color::Indexed(235)
+        );
+        assert_eq!(
+            depth.downsample(Color::Rgb(128, 128, 128)),
+            Color::Indexed(244)
+        );
+        assert_eq!(depth.downsample(Color::Indexed(42)), Color::Indexed(42));
+        assert_eq!(depth.downsample(Color::Cyan), Color::Cyan);
+    }
+
+    #[test]
+    fn test_downsample_to_16_colors() {
+        let depth = ColorDepth::Ansi16;
+        assert_eq!(depth.downsample(Color::Rgb(250, 10, 10)), Color::LightRed);
+        assert_eq!(depth.downsample(Color::Rgb(0x1e, 0x1e, 0x2e)), Color::Black);
+        assert_eq!(depth.downsample(Color::Indexed(4)), Color::Blue);
+        assert_eq!(depth.downsample(Color::Indexed(250)), Color::Gray);
+        assert_eq!(depth.downsample(Color::Reset), Color::Reset);
+    }
+
+    #[test]
+    fn test_downsample_buffer() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 2, 1));
+        buffer.set_style(
+            buffer.area,
+            Style::new()
+                .fg(Color::Rgb(255, 0, 0))
+                .bg(Color::Indexed(17)),
+        );
+        ColorDepth::NoColor.downsample_buffer(&mut buffer);
+        let cell = &buffer.content[0];
+        assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset));
+    }
+}
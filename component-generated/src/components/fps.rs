@@ -11,7 +11,7 @@ use ratatui::{
 
 use super::Component;
 
-use crate::action::Action;
+use crate::{action::Action, config::Config};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FpsCounter {
@@ -22,6 +22,10 @@ pub struct FpsCounter {
     last_frame_update: Instant,
     frame_count: u32,
     frames_per_second: f64,
+
+    style: Style,
+    /// Whether the rates changed since they were last drawn.
+    dirty: bool,
 }
 
 impl Default for FpsCounter {
@@ -39,6 +43,8 @@ impl FpsCounter {
             last_frame_update: Instant::now(),
             frame_count: 0,
             frames_per_second: 0.0,
+            style: Style::new().dim(),
+            dirty: false,
         }
     }
 
@@ -50,6 +56,7 @@ impl FpsCounter {
             self.ticks_per_second = self.tick_count as f64 / elapsed;
             self.last_tick_update = now;
             self.tick_count = 0;
+            self.dirty = true;
         }
         Ok(())
     }
@@ -62,12 +69,18 @@ impl FpsCounter {
             self.frames_per_second = self.frame_count as f64 / elapsed;
             self.last_frame_update = now;
             self.frame_count = 0;
+            self.dirty = true;
         }
         Ok(())
     }
 }
 
 impl Component for FpsCounter {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.style = config.theme.style("text.dim");
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         match action {
             Action::Tick => self.app_tick()?,
@@ -77,15 +90,34 @@ impl Component for FpsCounter {
         Ok(None)
     }
 
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        self.dirty = false;
         let [top, _] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
         let message = format!(
             "{:.2} ticks/sec, {:.2} FPS",
             self.ticks_per_second, self.frames_per_second
         );
-        let span = Span::styled(message, Style::new().dim());
+        let span = Span::styled(message, self.style);
         let paragraph = Paragraph::new(span).right_aligned();
         frame.render_widget(paragraph, top);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    #[test]
+    fn test_draw() {
+        let mut fps = FpsCounter::new();
+        assert_snapshot("fps_counter", 40, 2, |frame| {
+            fps.draw(frame, frame.area()).unwrap();
+        });
+    }
+}
@@ -0,0 +1,296 @@
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout, Margin, Rect},
+    text::Line,
+    widgets::{
+        Block, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
+        TableState,
+    },
+    Frame,
+};
+
+use super::{dialog::centered_area, Component};
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{key_sequence_to_string, Config},
+    theme::Theme,
+};
+
+/// A keybinding as it is shown in the help table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HelpEntry {
+    keys: String,
+    action: String,
+    description: String,
+}
+
+impl HelpEntry {
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        [&self.keys, &self.action, &self.description]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&filter))
+    }
+}
+
+/// Popup with a searchable, scrollable table of the keybindings of a mode.
+///
+/// The table is built from the merged [`Config`], so it reflects the bindings that users override
+/// in their own configuration files.
+pub struct Help {
+    mode: Mode,
+    entries: Vec<HelpEntry>,
+    filter: String,
+    searching: bool,
+    state: TableState,
+    theme: Theme,
+}
+
+impl Help {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            entries: Vec::new(),
+            filter: String::new(),
+            searching: false,
+            state: TableState::default().with_selected(0),
+            theme: Theme::default(),
+        }
+    }
+
+    fn visible_entries(&self) -> Vec<&HelpEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.matches(&self.filter))
+            .collect()
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.visible_entries().len();
+        let selected = self.state.selected().unwrap_or_default() as isize + offset;
+        self.state.select(Some(
+            selected.clamp(0, len.saturating_sub(1) as isize) as usize
+        ));
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.searching = false;
+            }
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.filter.push(c)
+            }
+            _ => {}
+        }
+        self.state.select(Some(0));
+        None
+    }
+}
+
+impl Component for Help {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        let keymap = config.keybindings.get(&self.mode);
+        self.entries = keymap
+            .into_iter()
+            .flatten()
+            .map(|(keys, action)| HelpEntry {
+                keys: key_sequence_to_string(keys),
+                action: format!("{action:?}"),
+                description: config
+                    .description(&action.to_string())
+                    .or(action.description())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect();
+        self.entries
+            .sort_by(|a, b| a.action.cmp(&b.action).then_with(|| a.keys.cmp(&b.keys)));
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if self.searching {
+            return Ok(self.handle_search_key(key));
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::F(1) => {
+                return Ok(Some(Action::CloseOverlay))
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::PageDown => self.select(10),
+            KeyCode::PageUp => self.select(-10),
+            KeyCode::Home | KeyCode::Char('g') => self.state.select(Some(0)),
+            KeyCode::End | KeyCode::Char('G') => self.select(isize::MAX / 2),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let popup = centered_area(area, (area.width * 4 / 5).max(40), area.height * 4 / 5);
+        let entries = self.visible_entries();
+        let key_width = entries
+            .iter()
+            .map(|entry| entry.keys.len())
+            .max()
+            .unwrap_or_default() as u16;
+        let action_width = entries
+            .iter()
+            .map(|entry| entry.action.len())
+            .max()
+            .unwrap_or_default() as u16;
+        let len = entries.len();
+        // owned, so that the table does not borrow `self` while the state is borrowed mutably
+        let rows: Vec<Row> = entries
+            .into_iter()
+            .map(|entry| {
+                Row::new([
+                    entry.keys.clone(),
+                    entry.action.clone(),
+                    entry.description.clone(),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(key_width.max(4)),
+                Constraint::Length(action_width.max(6)),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["Keys", "Action", "Description"]).style(self.theme.style("list.header")))
+        .column_spacing(2)
+        .row_highlight_style(self.theme.style("list.selected"));
+
+        let block = Block::bordered()
+            .title(" Help ")
+            .title_bottom(
+                Line::from(" </> search, <esc> close ").style(self.theme.style("text.dim")),
+            )
+            .border_style(self.theme.style("border.focused"));
+        let inner = block.inner(popup);
+        let [table_area, search_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner);
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(block, popup);
+        frame.render_stateful_widget(table, table_area, &mut self.state);
+        let mut scrollbar = ScrollbarState::new(len).position(self.state.selected().unwrap_or(0));
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            popup.inner(Margin::new(0, 1)),
+            &mut scrollbar,
+        );
+        let search = if self.searching || !self.filter.is_empty() {
+            Line::from(format!("/{}", self.filter))
+        } else {
+            Line::from("")
+        };
+        frame.render_widget(Paragraph::new(search), search_area);
+        if self.searching {
+            frame.set_cursor_position((
+                search_area.x + 1 + self.filter.chars().count() as u16,
+                search_area.y,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::{parse_key_sequence, KeyBindings};
+
+    fn config() -> Config {
+        let keymap = HashMap::from([
+            (parse_key_sequence("<q>").unwrap(), Action::Quit),
+            (parse_key_sequence("<ctrl-z>").unwrap(), Action::Suspend),
+            (parse_key_sequence("<tab>").unwrap(), Action::FocusNext),
+        ]);
+        Config {
+            keybindings: KeyBindings(HashMap::from([(Mode::Home, keymap)])),
+            descriptions: HashMap::from([("quit".to_string(), "Leave".to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_from_config() -> Result<()> {
+        let mut help = Help::new(Mode::Home);
+        help.register_config_handler(config())?;
+        assert_eq!(
+            help.entries,
+            vec![
+                HelpEntry {
+                    keys: "<tab>".to_string(),
+                    action: "FocusNext".to_string(),
+                    description: "Focus the next component".to_string(),
+                },
+                HelpEntry {
+                    keys: "<q>".to_string(),
+                    action: "Quit".to_string(),
+                    description: "Leave".to_string(),
+                },
+                HelpEntry {
+                    keys: "<ctrl-z>".to_string(),
+                    action: "Suspend".to_string(),
+                    description: "Suspend the application".to_string(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let mut help = Help::new(Mode::Home);
+        help.register_config_handler(config())?;
+        for c in "/ctrl".chars() {
+            help.handle_key_event(KeyCode::Char(c).into())?;
+        }
+        let visible: Vec<&str> = help
+            .visible_entries()
+            .iter()
+            .map(|entry| entry.action.as_str())
+            .collect();
+        assert_eq!(visible, vec!["Suspend"]);
+
+        help.handle_key_event(KeyCode::Esc.into())?;
+        assert_eq!(help.visible_entries().len(), 3);
+        assert_eq!(
+            help.handle_key_event(KeyCode::Esc.into())?,
+            Some(Action::CloseOverlay)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_is_clamped() -> Result<()> {
+        let mut help = Help::new(Mode::Home);
+        help.register_config_handler(config())?;
+        help.handle_key_event(KeyCode::PageDown.into())?;
+        assert_eq!(help.state.selected(), Some(2));
+        help.handle_key_event(KeyCode::Up.into())?;
+        assert_eq!(help.state.selected(), Some(1));
+        help.handle_key_event(KeyCode::PageUp.into())?;
+        assert_eq!(help.state.selected(), Some(0));
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{dialog::centered_area, Component};
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{key_sequence_to_string, Config},
+    theme::Theme,
+};
+
+/// An action that can be picked in the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PaletteEntry {
+    name: &'static str,
+    keys: String,
+    description: String,
+}
+
+/// Popup that fuzzy-matches over every [`Action`] variant and sends the chosen one.
+///
+/// The first word of the input selects the action, the rest is parsed as its arguments, e.g.
+/// `PushScreen Home` or `Confirm "Really quit?" Quit`.
+pub struct CommandPalette {
+    mode: Mode,
+    entries: Vec<PaletteEntry>,
+    input: String,
+    error: Option<String>,
+    state: TableState,
+    command_tx: Option<UnboundedSender<Action>>,
+    theme: Theme,
+}
+
+impl CommandPalette {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            entries: Vec::new(),
+            input: String::new(),
+            error: None,
+            state: TableState::default().with_selected(0),
+            command_tx: None,
+            theme: Theme::default(),
+        }
+    }
+
+    fn matches(&self) -> Vec<&PaletteEntry> {
+        matching_entries(&self.entries, &self.input)
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.matches().len();
+        let selected = self.state.selected().unwrap_or_default() as isize + offset;
+        self.state.select(Some(
+            selected.clamp(0, len.saturating_sub(1) as isize) as usize
+        ));
+    }
+
+    /// Parses the selected action with the arguments that were typed after its name.
+    fn selected_action(&self) -> Result<Action, String> {
+        let matches = self.matches();
+        let entry = matches
+            .get(self.state.selected().unwrap_or_default())
+            .ok_or_else(|| format!("No action matches `{}`", self.input))?;
+        let (_, arguments) = split_input(&self.input);
+        parse_action(&format!("{} {arguments}", entry.name))
+    }
+
+    fn run(&mut self) -> Result<Option<Action>> {
+        match self.selected_action() {
+            Ok(action) => {
+                if let Some(tx) = &self.command_tx {
+                    // close the palette first so that the action can open its own popups
+                    tx.send(Action::CloseOverlay)?;
+                    tx.send(action)?;
+                }
+                Ok(None)
+            }
+            Err(error) => {
+                self.error = Some(error);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Component for CommandPalette {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        let keymap = config.keybindings.get(&self.mode);
+        self.entries = Action::descriptions()
+            .map(|(name, description)| {
+                let mut keys: Vec<String> = keymap
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, action)| action.to_string() == name)
+                    .map(|(keys, _)| key_sequence_to_string(keys))
+                    .collect();
+                keys.sort();
+                PaletteEntry {
+                    name,
+                    keys: keys.join(" "),
+                    description: config
+                        .description(name)
+                        .or(description)
+                        .unwrap_or_default()
+                        .to_string(),
+                }
+            })
+            .collect();
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(Some(Action::CloseOverlay)),
+            KeyCode::Enter => return self.run(),
+            KeyCode::Down => self.select(1),
+            KeyCode::Up => self.select(-1),
+            KeyCode::Char('n') if control => self.select(1),
+            KeyCode::Char('p') if control => self.select(-1),
+            KeyCode::Tab => {
+                if let Some(entry) = self.matches().get(self.state.selected().unwrap_or(0)) {
+                    let (_, arguments) = split_input(&self.input);
+                    self.input = format!("{} {arguments}", entry.name);
+                    self.state.select(Some(0));
+                }
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.state.select(Some(0));
+            }
+            KeyCode::Char(c) if !control => {
+                self.input.push(c);
+                self.state.select(Some(0));
+            }
+            _ => {}
+        }
+        self.error = None;
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let popup = centered_area(area, (area.width * 3 / 5).max(40), area.height * 3 / 5);
+        let entries = matching_entries(&self.entries, &self.input);
+        let name_width = entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or_default() as u16;
+        let keys_width = entries
+            .iter()
+            .map(|entry| entry.keys.len())
+            .max()
+            .unwrap_or_default() as u16;
+        let rows = entries
+            .into_iter()
+            .map(|entry| Row::new([entry.name, entry.keys.as_str(), entry.description.as_str()]));
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(name_width),
+                Constraint::Length(keys_width),
+                Constraint::Fill(1),
+            ],
+        )
+        .column_spacing(2)
+        .row_highlight_style(self.theme.style("list.selected"));
+
+        let block = Block::bordered()
+            .title(" Command Palette ")
+            .title_bottom(
+                Line::from(" <enter> run, <tab> complete, <esc> close ")
+                    .style(self.theme.style("text.dim")),
+            )
+            .border_style(self.theme.style("border.focused"));
+        let inner = block.inner(popup);
+        let [input_area, status_area, list_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(block, popup);
+        frame.render_widget(Paragraph::new(format!("> {}", self.input)), input_area);
+        if let Some(error) = &self.error {
+            frame.render_widget(
+                Line::from(error.as_str()).style(self.theme.style("status.error")),
+                status_area,
+            );
+        }
+        frame.render_stateful_widget(table, list_area, &mut self.state);
+        frame.set_cursor_position((
+            input_area.x + 2 + self.input.chars().count() as u16,
+            input_area.y,
+        ));
+        Ok(())
+    }
+}
+
+/// The entries that match the action name being typed, best match first.
+fn matching_entries<'a>(entries: &'a [PaletteEntry], input: &str) -> Vec<&'a PaletteEntry> {
+    let (pattern, _) = split_input(input);
+    let mut matches: Vec<(i64, &PaletteEntry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(pattern, entry.name).map(|score| (score, entry)))
+        .collect();
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Splits the palette input into the action name and its arguments.
+fn split_input(input: &str) -> (&str, &str) {
+    let input = input.trim_start();
+    match input.split_once(char::is_whitespace) {
+        Some((name, arguments)) => (name, arguments.trim()),
+        None => (input, ""),
+    }
+}
+
+/// Parses an action from text such as `Quit`, `PushScreen Home`, `Resize 80 24` or
+/// `Confirm "Really quit?" Quit`.
+///
+/// The arguments are tried as a JSON value, as a single string and as a list of
+/// whitespace-separated values, in that order.
+pub fn parse_action(input: &str) -> Result<Action, String> {
+    let (name, arguments) = split_input(input);
+    if arguments.is_empty() {
+        return serde_json::from_value(Value::from(name))
+            .map_err(|_| format!("Unknown action `{name}` or missing arguments"));
+    }
+    let quote = |value: &str| match serde_json::from_str::<Value>(value) {
+        Ok(_) => value.to_string(),
+        Err(_) => Value::from(value).to_string(),
+    };
+    let list = split_arguments(arguments)
+        .iter()
+        .map(|argument| quote(argument))
+        .collect::<Vec<_>>()
+        .join(", ");
+    [
+        arguments.to_string(),
+        Value::from(arguments).to_string(),
+        format!("[{list}]"),
+    ]
+    .iter()
+    .filter_map(|value| serde_json::from_str::<Value>(value).ok())
+    .find_map(|value| serde_json::from_value(json!({ name: value })).ok())
+    .ok_or_else(|| format!("Unable to parse arguments `{arguments}` for `{name}`"))
+}
+
+/// Splits arguments at whitespace, keeping double quoted strings together.
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in arguments.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    result.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+/// Scores how well `pattern` fuzzy-matches `candidate`, or `None` if it does not match.
+///
+/// Every character of the pattern must appear in the candidate in order, ignoring case.
+/// Consecutive characters and characters at the start of a word score higher.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut previous_match: Option<usize> = None;
+    let mut candidate_chars = candidate.char_indices().peekable();
+    for p in pattern.chars() {
+        let p = p.to_ascii_lowercase();
+        let (index, c) = candidate_chars.find(|(_, c)| c.to_ascii_lowercase() == p)?;
+        score += 1;
+        if previous_match.is_some_and(|previous| index == previous + 1) {
+            score += 5;
+        }
+        if index == 0 || c.is_uppercase() {
+            score += 3;
+        }
+        previous_match = Some(index);
+    }
+    // prefer shorter candidates when the pattern matches equally well
+    Some(score * 100 - candidate.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use ratatui::{backend::TestBackend, Terminal};
+    use strum::IntoEnumIterator;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::{parse_key_sequence, KeyBindings};
+
+    fn palette() -> Result<CommandPalette> {
+        let keymap = HashMap::from([(parse_key_sequence("<q>").unwrap(), Action::Quit)]);
+        let config = Config {
+            keybindings: KeyBindings(HashMap::from([(Mode::Home, keymap)])),
+            ..Default::default()
+        };
+        let mut palette = CommandPalette::new(Mode::Home);
+        palette.register_config_handler(config)?;
+        Ok(palette)
+    }
+
+    fn type_text(palette: &mut CommandPalette, text: &str) -> Result<()> {
+        for c in text.chars() {
+            palette.handle_key_event(KeyCode::Char(c).into())?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_action_has_a_description() -> Result<()> {
+        let palette = palette()?;
+        let names: Vec<String> = Action::iter().map(|action| action.to_string()).collect();
+        assert_eq!(
+            palette
+                .entries
+                .iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>(),
+            names
+        );
+        for entry in &palette.entries {
+            assert!(!entry.description.is_empty(), "{}", entry.name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns_fit_the_keys() -> Result<()> {
+        let keymap = ["<ctrl-c>", "<ctrl-d>", "<q>"]
+            .map(|keys| (parse_key_sequence(keys).unwrap(), Action::Quit));
+        let mut palette = CommandPalette::new(Mode::Home);
+        palette.register_config_handler(Config {
+            keybindings: KeyBindings(HashMap::from([(Mode::Home, HashMap::from(keymap))])),
+            ..Default::default()
+        })?;
+        type_text(&mut palette, "quit")?;
+        let mut terminal = Terminal::new(TestBackend::new(80, 12))?;
+        terminal.draw(|frame| palette.draw(frame, frame.area()).unwrap())?;
+        let buffer = terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.contains("Quit  <ctrl-c> <ctrl-d> <q>  Quit")),
+            "{lines:#?}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("fn", "FocusNext").is_some());
+        assert!(fuzzy_score("nf", "FocusNext").is_none());
+        assert!(fuzzy_score("", "Quit").is_some());
+        assert!(fuzzy_score("quit", "Quit") > fuzzy_score("qt", "Quit"));
+        assert!(fuzzy_score("pop", "PopScreen") > fuzzy_score("pop", "FocusPrevious"));
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(parse_action("Quit"), Ok(Action::Quit));
+        assert_eq!(
+            parse_action("PushScreen Home"),
+            Ok(Action::PushScreen(Mode::Home))
+        );
+        assert_eq!(parse_action("Resize 80 24"), Ok(Action::Resize(80, 24)));
+        assert_eq!(
+            parse_action("Error something went wrong"),
+            Ok(Action::Error("something went wrong".to_string()))
+        );
+        assert_eq!(
+            parse_action(r#"Confirm "Really quit?" Quit"#),
+            Ok(Action::Confirm(
+                "Really quit?".to_string(),
+                Box::new(Action::Quit)
+            ))
+        );
+        assert!(parse_action("PushScreen").is_err());
+        assert!(parse_action("Unknown").is_err());
+    }
+
+    #[test]
+    fn test_entries_show_bindings() -> Result<()> {
+        let palette = palette()?;
+        let quit = palette
+            .entries
+            .iter()
+            .find(|entry| entry.name == "Quit")
+            .unwrap();
+        assert_eq!(quit.keys, "<q>");
+        assert_eq!(quit.description, "Quit the application");
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_selected_action() -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut palette = palette()?;
+        palette.register_action_handler(tx)?;
+        type_text(&mut palette, "pushs Home")?;
+        palette.handle_key_event(KeyCode::Enter.into())?;
+        assert_eq!(rx.try_recv().ok(), Some(Action::CloseOverlay));
+        assert_eq!(rx.try_recv().ok(), Some(Action::PushScreen(Mode::Home)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_reports_errors() -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut palette = palette()?;
+        palette.register_action_handler(tx)?;
+        type_text(&mut palette, "pushscreen")?;
+        palette.handle_key_event(KeyCode::Enter.into())?;
+        assert!(rx.try_recv().is_err());
+        assert!(palette.error.is_some());
+        Ok(())
+    }
+}
@@ -6,20 +6,42 @@ use crate::app::App;
 
 mod action;
 mod app;
+mod background;
 mod cli;
+mod color_depth;
+mod commands;
 mod components;
 mod config;
 mod errors;
+mod keymap;
+mod layout;
 mod logging;
+mod recording;
+mod screen;
+#[cfg(test)]
+mod snapshot;
+mod state;
+mod theme;
 mod tui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     crate::errors::init()?;
+    let args = Cli::parse();
+    let config_sources = args.config_sources();
+    if let Some(command) = args.command {
+        return crate::commands::run(command, config_sources);
+    }
     crate::logging::init()?;
 
-    let args = Cli::parse();
-    let mut app = App::new(args.tick_rate, args.frame_rate)?;
+    let mut app = App::new(config_sources, args.lenient, args.color_depth)?;
+    app.viewport(args.viewport);
+    if let Some(path) = &args.record {
+        app.record(path)?;
+    }
+    if let Some(path) = &args.replay {
+        app.replay(path)?;
+    }
     app.run().await?;
     Ok(())
 }
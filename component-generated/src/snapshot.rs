@@ -0,0 +1,108 @@
+//! Snapshot tests for components and widgets.
+//!
+//! [`assert_snapshot`] draws into a [`TestBackend`] and compares the buffer, styles included, with
+//! a text file in `src/snapshots`. Run the tests with `UPDATE_SNAPSHOTS=1` to write the snapshots
+//! that are missing or changed instead, and review the difference before committing it.
+
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+use pretty_assertions::assert_eq;
+use ratatui::{
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    style::{Color, Style},
+    text::Span,
+    Frame, Terminal,
+};
+
+/// Set to `1` to write the snapshots instead of comparing with them.
+pub const UPDATE_SNAPSHOTS: &str = "UPDATE_SNAPSHOTS";
+
+/// Draws into a `width` x `height` buffer with `draw`, and compares the result with the snapshot
+/// `name`.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut home = Home::new();
+/// assert_snapshot("home", 20, 3, |frame| home.draw(frame, frame.area()).unwrap());
+/// ```
+#[track_caller]
+pub fn assert_snapshot(name: &str, width: u16, height: u16, draw: impl FnOnce(&mut Frame)) {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(draw).unwrap();
+    let actual = buffer_to_string(terminal.backend().buffer());
+    let path = snapshot_path(name);
+    if env::var(UPDATE_SNAPSHOTS).is_ok_and(|value| value == "1") {
+        if fs::read_to_string(&path).ok().as_deref() != Some(actual.as_str()) {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &actual).unwrap();
+        }
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "The snapshot {} does not exist, run the tests with {UPDATE_SNAPSHOTS}=1 to write it:\n{actual}",
+            path.display()
+        );
+    };
+    assert_eq!(
+        expected,
+        actual,
+        "The snapshot {} differs, run the tests with {UPDATE_SNAPSHOTS}=1 to update it",
+        path.display()
+    );
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "src", "snapshots"]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{name}.snap"))
+}
+
+/// Writes the symbols of the buffer line by line, followed by the styled runs of cells as
+/// `y x..x: style`, e.g. `0 0..6: Style::new().cyan().bold()`.
+fn buffer_to_string(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    let mut text = String::new();
+    for y in area.top()..area.bottom() {
+        // the cells that a wide symbol covers are not written
+        let mut skip = 0;
+        for x in area.left()..area.right() {
+            let symbol = buffer[(x, y)].symbol();
+            if skip == 0 {
+                text.push_str(symbol);
+            }
+            skip = skip.max(Span::raw(symbol).width()).saturating_sub(1);
+        }
+        text.push('\n');
+    }
+    text.push_str("--- styles ---\n");
+    for y in area.top()..area.bottom() {
+        let mut x = area.left();
+        while x < area.right() {
+            let style = cell_style(&buffer[(x, y)]);
+            let start = x;
+            while x < area.right() && cell_style(&buffer[(x, y)]) == style {
+                x += 1;
+            }
+            if style != Style::new() {
+                writeln!(text, "{y} {start}..{x}: {style:?}").unwrap();
+            }
+        }
+    }
+    text
+}
+
+/// The foreground, background and modifiers of a cell, without the colors that only reset the
+/// terminal, which every cell of a `TestBackend` starts with.
+fn cell_style(cell: &Cell) -> Style {
+    let color = |color| Some(color).filter(|color| *color != Color::Reset);
+    Style {
+        fg: color(cell.fg),
+        bg: color(cell.bg),
+        add_modifier: cell.modifier,
+        ..Style::new()
+    }
+}
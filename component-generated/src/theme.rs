@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use ratatui::style::Style;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{
+    background::Brightness,
+    config::{style_to_string, Styles},
+};
+
+/// The theme that is used when the configuration does not select one.
+pub const DEFAULT_THEME: &str = "dark";
+
+/// The theme name that picks the `light` or `dark` theme depending on the background of the
+/// terminal.
+pub const AUTO_THEME: &str = "auto";
+
+/// A theme as it is written in the configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThemeConfig {
+    /// The theme that the styles which are not defined here are taken from.
+    pub extends: Option<String>,
+    pub styles: Styles,
+}
+
+impl Serialize for ThemeConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut styles: Vec<_> = self.styles.iter().collect();
+        styles.sort_by_key(|(key, _)| *key);
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(extends) = &self.extends {
+            map.serialize_entry("extends", extends)?;
+        }
+        for (key, style) in styles {
+            map.serialize_entry(key, &style_to_string(style))?;
+        }
+        map.end()
+    }
+}
+
+/// Named set of styles for semantic keys such as `border.focused` or `status.error`.
+///
+/// Keys inherit from their parent: `border.focused` is the style of `border` patched with the
+/// style of `border.focused`, and every key is patched onto `default`. A theme therefore only
+/// has to define the keys that differ from their parent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    styles: HashMap<String, Style>,
+}
+
+impl Theme {
+    /// Builds the theme `name` from `themes`, including the styles that it inherits through
+    /// `extends`.
+    pub fn resolve(name: &str, themes: &HashMap<String, ThemeConfig>) -> Result<Self, String> {
+        let mut chain: Vec<(&str, &ThemeConfig)> = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if let Some(start) = chain.iter().position(|(seen, _)| *seen == name) {
+                let cycle: Vec<&str> = chain[start..]
+                    .iter()
+                    .map(|(seen, _)| *seen)
+                    .chain([name])
+                    .collect();
+                return Err(format!("Themes {} form a cycle", cycle.join(" → ")));
+            }
+            let theme = themes
+                .get(name)
+                .ok_or_else(|| format!("Unknown theme `{name}`"))?;
+            chain.push((name, theme));
+            next = theme.extends.as_deref();
+        }
+        let mut styles = HashMap::new();
+        for (_, theme) in chain.iter().rev() {
+            styles.extend(
+                theme
+                    .styles
+                    .iter()
+                    .map(|(key, style)| (key.clone(), *style)),
+            );
+        }
+        Ok(Self {
+            name: name.to_string(),
+            styles,
+        })
+    }
+
+    /// Like [`Theme::resolve`], but [`AUTO_THEME`] picks the `light` or `dark` theme depending on
+    /// the `background` of the terminal, and the dark one if the background is not known.
+    pub fn select(
+        name: &str,
+        themes: &HashMap<String, ThemeConfig>,
+        background: Option<Brightness>,
+    ) -> Result<Self, String> {
+        let name = match (name, background) {
+            (AUTO_THEME, Some(Brightness::Light)) => "light",
+            (AUTO_THEME, _) => "dark",
+            (name, _) => name,
+        };
+        Self::resolve(name, themes)
+    }
+
+    /// The style of a semantic key, e.g. `list.selected`.
+    pub fn style(&self, key: &str) -> Style {
+        let mut style = self.styles.get("default").copied().unwrap_or_default();
+        let ends = key
+            .match_indices('.')
+            .map(|(index, _)| index)
+            .chain([key.len()]);
+        for end in ends {
+            if let Some(parent) = self.styles.get(&key[..end]) {
+                style = style.patch(*parent);
+            }
+        }
+        style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use ratatui::style::{Color, Modifier, Stylize};
+
+    use super::*;
+
+    fn theme(extends: Option<&str>, styles: &[(&str, Style)]) -> ThemeConfig {
+        ThemeConfig {
+            extends: extends.map(str::to_string),
+            styles: Styles(
+                styles
+                    .iter()
+                    .map(|(key, style)| (key.to_string(), *style))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn themes() -> HashMap<String, ThemeConfig> {
+        HashMap::from([
+            (
+                "dark".to_string(),
+                theme(
+                    None,
+                    &[
+                        ("default", Style::new().white()),
+                        ("border", Style::new().gray()),
+                        ("border.focused", Style::new().cyan().bold()),
+                    ],
+                ),
+            ),
+            (
+                "custom".to_string(),
+                theme(Some("dark"), &[("border", Style::new().blue())]),
+            ),
+            ("loop".to_string(), theme(Some("loop"), &[])),
+            ("ping".to_string(), theme(Some("pong"), &[])),
+            ("pong".to_string(), theme(Some("ping"), &[])),
+            ("serve".to_string(), theme(Some("ping"), &[])),
+        ])
+    }
+
+    #[test]
+    fn test_keys_inherit_from_parents() -> Result<(), String> {
+        let theme = Theme::resolve("dark", &themes())?;
+        assert_eq!(theme.style("text"), Style::new().white());
+        assert_eq!(theme.style("border.unfocused"), Style::new().gray());
+        let focused = theme.style("border.focused.title");
+        assert_eq!(focused.fg, Some(Color::Cyan));
+        assert!(focused.add_modifier.contains(Modifier::BOLD));
+        Ok(())
+    }
+
+    #[test]
+    fn test_themes_inherit_from_extended_theme() -> Result<(), String> {
+        let theme = Theme::resolve("custom", &themes())?;
+        assert_eq!(theme.style("border"), Style::new().blue());
+        assert_eq!(theme.style("border.focused").fg, Some(Color::Cyan));
+        assert_eq!(theme.style("text"), Style::new().white());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_theme_follows_background() -> Result<(), String> {
+        let mut themes = themes();
+        themes.insert("light".to_string(), theme(None, &[]));
+        let select = |background| Theme::select(AUTO_THEME, &themes, background);
+        assert_eq!(select(Some(Brightness::Light))?.name, "light");
+        assert_eq!(select(Some(Brightness::Dark))?.name, "dark");
+        assert_eq!(select(None)?.name, "dark");
+        assert_eq!(
+            Theme::select("custom", &themes, Some(Brightness::Light))?.name,
+            "custom"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_themes() {
+        assert_eq!(
+            Theme::resolve("missing", &themes()),
+            Err("Unknown theme `missing`".to_string())
+        );
+        assert_eq!(
+            Theme::resolve("loop", &themes()),
+            Err("Themes loop → loop form a cycle".to_string())
+        );
+        assert_eq!(
+            Theme::resolve("serve", &themes()),
+            Err("Themes ping → pong → ping form a cycle".to_string())
+        );
+    }
+}
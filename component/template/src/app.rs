@@ -1,26 +1,59 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
 use color_eyre::Result;
-use crossterm::event::KeyEvent;
-use ratatui::prelude::Rect;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{backend::Backend, prelude::Rect, text::Line, Viewport};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
     action::Action,
-    components::{fps::FpsCounter, home::Home, Component},
-    config::Config,
+    background::Brightness,
+    color_depth::ColorDepth,
+    components::{
+        dialog::{ConfirmDialog, ErrorDialog},
+        help::Help,
+        palette::CommandPalette,
+        Component, ComponentTree,
+    },
+    config::{self, Config, ConfigError, ConfigSources},
+    keymap::{self, KeyMatch, KeySequence},
+    recording::{self, RecordedEvent, Recorder},
+    screen::Screen,
+    state::{self, State},
+    theme::Theme,
     tui::{Event, Tui},
 };
 
 pub struct App {
     config: Config,
-    tick_rate: f64,
-    frame_rate: f64,
-    components: Vec<Box<dyn Component>>,
+    /// Where the configuration is read from, also when it is reloaded.
+    config_sources: ConfigSources,
+    lenient_config: bool,
+    /// Overrides the color depth that is detected from the environment.
+    color_depth: Option<ColorDepth>,
+    screens: Vec<Screen>,
+    overlays: Vec<Box<dyn Component>>,
     should_quit: bool,
     should_suspend: bool,
     mode: Mode,
-    last_tick_key_events: Vec<KeyEvent>,
+    key_sequence: KeySequence,
+    /// Whether a new frame has to be drawn, besides the components that report it through
+    /// [`Component::is_dirty`].
+    dirty: bool,
+    /// Whether the background of the terminal is light or dark, once it is known.
+    background: Option<Brightness>,
+    /// Writes the events that are received to a file, see [`App::record`].
+    recorder: Option<Recorder>,
+    /// Events to replay instead of waiting for the terminal, see [`App::replay`].
+    replay: Option<Vec<RecordedEvent>>,
+    viewport: Viewport,
+    /// Where the state is saved across restarts, `None` to not save it.
+    state_file: Option<PathBuf>,
     action_tx: mpsc::UnboundedSender<Action>,
     action_rx: mpsc::UnboundedReceiver<Action>,
 }
@@ -32,49 +65,103 @@ pub enum Mode {
 }
 
 impl App {
-    pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
+    pub fn new(
+        config_sources: ConfigSources,
+        lenient_config: bool,
+        color_depth: Option<ColorDepth>,
+    ) -> Result<Self> {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let config = load_config(&config_sources, lenient_config, &action_tx)?;
+        let mode = Mode::Home;
+        let screens = vec![Screen::new(mode, &config)?];
+        let key_sequence =
+            KeySequence::new(Duration::from_millis(config.config.key_sequence_timeout));
         Ok(Self {
-            tick_rate,
-            frame_rate,
-            components: vec![Box::new(Home::new()), Box::new(FpsCounter::default())],
+            screens,
+            overlays: Vec::new(),
             should_quit: false,
             should_suspend: false,
-            config: Config::new()?,
-            mode: Mode::Home,
-            last_tick_key_events: Vec::new(),
+            config,
+            config_sources,
+            lenient_config,
+            color_depth,
+            mode,
+            key_sequence,
+            dirty: true,
+            background: None,
+            recorder: None,
+            replay: None,
+            viewport: Viewport::Fullscreen,
+            state_file: Some(state::state_file()),
             action_tx,
             action_rx,
         })
     }
 
+    /// Records every event to `path` as JSON lines, so that the session can be replayed.
+    pub fn record(&mut self, path: &Path) -> Result<()> {
+        self.recorder = Some(Recorder::create(path)?);
+        Ok(())
+    }
+
+    /// Replays the events recorded in `path` before reading events from the terminal.
+    pub fn replay(&mut self, path: &Path) -> Result<()> {
+        self.replay = Some(recording::read_recording(path)?);
+        Ok(())
+    }
+
+    /// Draws the application inline below the shell prompt or in a fixed area instead of the
+    /// whole screen.
+    pub fn viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         let mut tui = Tui::new()?
             // .mouse(true) // uncomment this line to enable mouse support
-            .tick_rate(self.tick_rate)
-            .frame_rate(self.frame_rate);
-        tui.enter()?;
-
-        for component in self.components.iter_mut() {
-            component.register_action_handler(self.action_tx.clone())?;
+            .tick_rate(self.config.config.tick_rate)
+            .frame_rate(self.config.config.frame_rate)
+            .viewport(self.viewport.clone())
+            .keyboard_enhancement(self.config.config.keyboard_enhancement);
+        if let Some(color_depth) = self.color_depth {
+            tui = tui.color_depth(color_depth);
         }
-        for component in self.components.iter_mut() {
-            component.register_config_handler(self.config.clone())?;
+        if let Some(events) = self.replay.take() {
+            tui = tui.replay(events);
         }
-        for component in self.components.iter_mut() {
-            component.init(tui.size()?)?;
+        // The watcher stops when it is dropped at the end of `run`.
+        let _config_watcher = config::watch_config(&self.config_sources, self.action_tx.clone())
+            .inspect_err(|err| warn!("Configuration changes will not be reloaded: {err}"))
+            .ok();
+        self.run_with(&mut tui).await
+    }
+
+    /// Runs the application in `tui` until it quits. [`App::run`] runs it in the terminal, tests
+    /// can run it against a `TestBackend` with scripted events.
+    pub async fn run_with<B: Backend>(&mut self, tui: &mut Tui<B>) -> Result<()> {
+        debug!("Color depth: {:?}", tui.color_depth);
+        tui.enter()?;
+        self.background = tui.background.map(|background| background.brightness());
+        self.config = self.with_selected_theme(self.config.clone());
+
+        self.restore_state();
+        let area = tui.viewport_area();
+        for screen in self.screens.iter_mut() {
+            screen.init(self.action_tx.clone(), &self.config, area)?;
         }
 
         let action_tx = self.action_tx.clone();
         loop {
-            self.handle_events(&mut tui).await?;
-            self.handle_actions(&mut tui)?;
+            self.handle_events(tui).await?;
+            self.handle_actions(tui)?;
             if self.should_suspend {
                 tui.suspend()?;
                 action_tx.send(Action::Resume)?;
                 action_tx.send(Action::ClearScreen)?;
                 // tui.mouse(true);
                 tui.enter()?;
+                let area = tui.viewport_area();
+                self.screen_mut().layout.resize(area);
             } else if self.should_quit {
                 tui.stop()?;
                 break;
@@ -84,21 +171,50 @@ impl App {
         Ok(())
     }
 
-    async fn handle_events(&mut self, tui: &mut Tui) -> Result<()> {
+    async fn handle_events<B: Backend>(&mut self, tui: &mut Tui<B>) -> Result<()> {
         let Some(event) = tui.next_event().await else {
             return Ok(());
         };
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(err) = recorder.record(&event) {
+                warn!("Stopped recording the events: {err}");
+                self.recorder = None;
+            }
+        }
+        // releases are only drawn when they are bound, see `handle_key_event`
+        if matches!(event, Event::Mouse(_) | Event::Paste(_))
+            || matches!(event, Event::Key(key) if key.kind != KeyEventKind::Release)
+        {
+            self.dirty = true;
+        }
         let action_tx = self.action_tx.clone();
+        let was_pending = self.key_sequence.is_pending();
+        if let Some((action, count)) = self.key_sequence.expire(Instant::now()) {
+            self.send_repeated(action, count)?;
+        }
+        if was_pending && !self.key_sequence.is_pending() {
+            // the pending keys are no longer shown
+            self.dirty = true;
+        }
         match event {
             Event::Quit => action_tx.send(Action::Quit)?,
             Event::Tick => action_tx.send(Action::Tick)?,
             Event::Render => action_tx.send(Action::Render)?,
             Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
-            Event::Key(key) => self.handle_key_event(key)?,
+            Event::Key(key) => return self.handle_key_event(key),
             _ => {}
         }
-        for component in self.components.iter_mut() {
-            if let Some(action) = component.handle_events(Some(event.clone()))? {
+        // While an overlay is open it captures all input.
+        if let Some(overlay) = self.overlays.last_mut() {
+            if matches!(event, Event::Mouse(_) | Event::Paste(_)) {
+                if let Some(action) = overlay.handle_events_tree(Some(event))? {
+                    action_tx.send(action)?;
+                }
+                return Ok(());
+            }
+        }
+        for (_, component) in self.screen_mut().layout.components_mut() {
+            if let Some(action) = component.handle_events_tree(Some(event.clone()))? {
                 action_tx.send(action)?;
             }
         }
@@ -107,71 +223,692 @@ impl App {
 
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
         let action_tx = self.action_tx.clone();
-        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
+        // Components see presses and repeats like they do without the kitty keyboard protocol.
+        // Releases are only matched against the keybindings.
+        let is_release = key.kind == KeyEventKind::Release;
+        if !self.overlays.is_empty() && !is_release {
+            if let Some(action) = self.global_action(key) {
+                action_tx.send(action)?;
+                return Ok(());
+            }
+        }
+        if let Some(overlay) = self.overlays.last_mut() {
+            if is_release {
+                return Ok(());
+            }
+            if let Some(action) = overlay.handle_events_tree(Some(Event::Key(key)))? {
+                action_tx.send(action)?;
+            }
             return Ok(());
-        };
-        match keymap.get(&vec![key]) {
-            Some(action) => {
-                info!("Got action: {action:?}");
-                action_tx.send(action.clone())?;
-            }
-            _ => {
-                // If the key was not handled as a single key action,
-                // then consider it for multi-key combinations.
-                self.last_tick_key_events.push(key);
-
-                // Check for multi-key combinations
-                if let Some(action) = keymap.get(&self.last_tick_key_events) {
-                    info!("Got action: {action:?}");
-                    action_tx.send(action.clone())?;
+        }
+        // The focused component gets the first chance to handle the key, unless a count or
+        // multi-key binding is being typed. Keys that it does not handle bubble up to the
+        // keybindings of the current mode.
+        if !self.key_sequence.is_pending() && !is_release {
+            if let Some(component) = self.screen_mut().focused_component_mut() {
+                if let Some(action) = component.handle_events_tree(Some(Event::Key(key)))? {
+                    action_tx.send(action)?;
+                    return Ok(());
                 }
             }
         }
+        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
+            return Ok(());
+        };
+        let Some(key) = keymap::normalize_key(key, keymap) else {
+            return Ok(());
+        };
+        self.dirty = true;
+        if let KeyMatch::Action { action, count } =
+            self.key_sequence.push(key, keymap, Instant::now())
+        {
+            info!("Got action: {action:?}");
+            self.send_repeated(action, count)?;
+        }
+        Ok(())
+    }
+
+    /// The [`Action::Quit`] or [`Action::Suspend`] that `key` is bound to on its own, which works
+    /// even while an overlay captures the input. Keys that type text, like `q`, are left to the
+    /// overlay so that they can still be typed into the command palette.
+    fn global_action(&self, key: KeyEvent) -> Option<Action> {
+        let types_text = matches!(key.code, KeyCode::Char(_))
+            && key.modifiers.difference(KeyModifiers::SHIFT).is_empty();
+        if types_text {
+            return None;
+        }
+        let keymap = self.config.keybindings.get(&self.mode)?;
+        let key = keymap::normalize_key(key, keymap)?;
+        match keymap.get(&vec![key])? {
+            action @ (Action::Quit | Action::Suspend) => Some(action.clone()),
+            _ => None,
+        }
+    }
+
+    /// Sends an action once for every time it was requested by a count prefix.
+    fn send_repeated(&self, action: Action, count: usize) -> Result<()> {
+        for _ in 0..count {
+            self.action_tx.send(action.clone())?;
+        }
         Ok(())
     }
 
-    fn handle_actions(&mut self, tui: &mut Tui) -> Result<()> {
+    fn handle_actions<B: Backend>(&mut self, tui: &mut Tui<B>) -> Result<()> {
         while let Ok(action) = self.action_rx.try_recv() {
             if action != Action::Tick && action != Action::Render {
                 debug!("{action:?}");
+                self.dirty = true;
             }
             match action {
-                Action::Tick => {
-                    self.last_tick_key_events.drain(..);
+                Action::Tick => {}
+                Action::Quit => {
+                    self.save_state();
+                    self.should_quit = true
+                }
+                Action::Suspend => {
+                    self.save_state();
+                    self.should_suspend = true
                 }
-                Action::Quit => self.should_quit = true,
-                Action::Suspend => self.should_suspend = true,
                 Action::Resume => self.should_suspend = false,
                 Action::ClearScreen => tui.terminal.clear()?,
                 Action::Resize(w, h) => self.handle_resize(tui, w, h)?,
                 Action::Render => self.render(tui)?,
-                _ => {}
+                Action::FocusNext => self.screen_mut().focus_next()?,
+                Action::FocusPrevious => self.screen_mut().focus_previous()?,
+                Action::PushScreen(mode) => self.push_screen(tui, mode)?,
+                Action::PopScreen => self.pop_screen(tui)?,
+                Action::ReplaceScreen(mode) => self.replace_screen(tui, mode)?,
+                Action::Error(ref message) => {
+                    self.open_overlay(Box::new(ErrorDialog::new(message.clone())))?
+                }
+                Action::Confirm(ref message, ref confirmed) => self.open_overlay(Box::new(
+                    ConfirmDialog::new(message.clone(), *confirmed.clone()),
+                ))?,
+                Action::Help => self.open_overlay(Box::new(Help::new(self.mode)))?,
+                Action::CommandPalette => {
+                    self.open_overlay(Box::new(CommandPalette::new(self.mode)))?
+                }
+                Action::CloseOverlay => {
+                    self.overlays.pop();
+                }
+                Action::Print(ref text) => tui.print_above(text)?,
+                Action::ReloadConfig => self.reload_config(tui.viewport_area())?,
+                Action::SetTheme(ref name) => self.set_theme(name)?,
+            }
+            let action_tx = self.action_tx.clone();
+            for (_, component) in self.screen_mut().layout.components_mut() {
+                for action in component.update_tree(action.clone())? {
+                    action_tx.send(action)?
+                }
+            }
+            for overlay in self.overlays.iter_mut() {
+                for action in overlay.update_tree(action.clone())? {
+                    action_tx.send(action)?
+                }
+            }
+        }
+        if self.is_dirty() {
+            tui.request_render();
+        }
+        Ok(())
+    }
+
+    /// Whether something changed since the last frame, so that a new one has to be drawn.
+    fn is_dirty(&mut self) -> bool {
+        self.dirty
+            || self
+                .screen_mut()
+                .layout
+                .components_mut()
+                .into_iter()
+                .any(|(_, component)| component.is_dirty_tree())
+            || self
+                .overlays
+                .iter_mut()
+                .any(|overlay| overlay.is_dirty_tree())
+    }
+
+    /// The screen on top of the stack, which receives events and is drawn.
+    fn screen_mut(&mut self) -> &mut Screen {
+        self.screens
+            .last_mut()
+            .expect("the screen stack is never empty")
+    }
+
+    /// Reads the configuration files again, passes the new configuration to every component and
+    /// arranges the screens in `area` as the new layouts describe. If the configuration is
+    /// invalid, the errors are shown and the previous configuration is kept.
+    fn reload_config(&mut self, area: Rect) -> Result<()> {
+        let config = match load_config(&self.config_sources, self.lenient_config, &self.action_tx) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("Failed to reload the configuration: {err}");
+                self.action_tx.send(Action::Error(format!(
+                    "Failed to reload the configuration: {err}"
+                )))?;
+                return Ok(());
             }
-            for component in self.components.iter_mut() {
-                if let Some(action) = component.update(action.clone())? {
-                    self.action_tx.send(action)?
-                };
+        };
+        info!("Reloaded the configuration");
+        self.apply_config(config)?;
+        for screen in self.screens.iter_mut() {
+            if let Err(err) = screen.relayout(&self.config, area) {
+                warn!("Failed to apply the layout of {:?}: {err}", screen.mode);
+                self.action_tx.send(Action::Error(format!(
+                    "The layout of {:?} was not changed: {err}",
+                    screen.mode
+                )))?;
             }
         }
         Ok(())
     }
 
-    fn handle_resize(&mut self, tui: &mut Tui, w: u16, h: u16) -> Result<()> {
-        tui.resize(Rect::new(0, 0, w, h))?;
+    /// Restyles every component with the theme `name`.
+    fn set_theme(&mut self, name: &str) -> Result<()> {
+        if let Err(reason) = Theme::select(name, &self.config.themes, self.background) {
+            self.action_tx.send(Action::Error(reason))?;
+            return Ok(());
+        }
+        let mut config = self.config.clone();
+        config.config.theme = name.to_string();
+        self.apply_config(config)
+    }
+
+    /// Resolves the theme selected in `config` now that the background of the terminal is known,
+    /// so that `auto` picks the light theme on a light background.
+    fn with_selected_theme(&self, mut config: Config) -> Config {
+        match Theme::select(&config.config.theme, &config.themes, self.background) {
+            Ok(theme) => config.theme = theme,
+            Err(reason) => warn!("{reason}"),
+        }
+        config
+    }
+
+    /// Makes `config` the configuration in use and passes it to every component.
+    fn apply_config(&mut self, config: Config) -> Result<()> {
+        let config = self.with_selected_theme(config);
+        for screen in self.screens.iter_mut() {
+            screen.register_config(&config)?;
+        }
+        for overlay in self.overlays.iter_mut() {
+            overlay.register_config_handler_tree(config.clone())?;
+        }
+        self.key_sequence =
+            KeySequence::new(Duration::from_millis(config.config.key_sequence_timeout));
+        self.config = config;
+        Ok(())
+    }
+
+    /// Opens a popup on top of the screen. It receives all input until it is closed with
+    /// [`Action::CloseOverlay`].
+    fn open_overlay(&mut self, mut overlay: Box<dyn Component>) -> Result<()> {
+        overlay.register_action_handler_tree(self.action_tx.clone())?;
+        overlay.register_config_handler_tree(self.config.clone())?;
+        self.overlays.push(overlay);
+        Ok(())
+    }
+
+    /// Shows a new screen for `mode` on top of the current one.
+    fn push_screen<B: Backend>(&mut self, tui: &mut Tui<B>, mode: Mode) -> Result<()> {
+        let mut screen = Screen::new(mode, &self.config)?;
+        let area = tui.viewport_area();
+        screen.init(self.action_tx.clone(), &self.config, area)?;
+        self.screen_mut().hide()?;
+        self.screens.push(screen);
+        self.set_mode(mode);
+        Ok(())
+    }
+
+    /// Closes the current screen and restores the one below it with the state it was left in.
+    fn pop_screen<B: Backend>(&mut self, tui: &mut Tui<B>) -> Result<()> {
+        if self.screens.len() == 1 {
+            warn!("Cannot pop the last screen");
+            return Ok(());
+        }
+        if let Some(mut screen) = self.screens.pop() {
+            screen.hide()?;
+        }
+        let area = tui.viewport_area();
+        let screen = self.screen_mut();
+        screen.show(area)?;
+        let mode = screen.mode;
+        self.set_mode(mode);
+        Ok(())
+    }
+
+    /// Replaces the current screen with a new screen for `mode`.
+    fn replace_screen<B: Backend>(&mut self, tui: &mut Tui<B>, mode: Mode) -> Result<()> {
+        let mut screen = Screen::new(mode, &self.config)?;
+        let area = tui.viewport_area();
+        screen.init(self.action_tx.clone(), &self.config, area)?;
+        self.screen_mut().hide()?;
+        *self.screen_mut() = screen;
+        self.set_mode(mode);
+        Ok(())
+    }
+
+    /// Rebuilds the screens that were open when the state was last saved. Nothing is restored if
+    /// the state cannot be read or a saved screen can no longer be built.
+    fn restore_state(&mut self) {
+        let Some(state) = self.state_file.as_deref().and_then(State::load) else {
+            return;
+        };
+        let screens = state
+            .screens
+            .iter()
+            .map(|saved| {
+                let mut screen = Screen::new(saved.mode, &self.config)?;
+                screen.restore_state(saved);
+                Ok(screen)
+            })
+            .collect::<Result<Vec<_>>>();
+        match screens {
+            Ok(screens) if !screens.is_empty() => {
+                let mode = screens[screens.len() - 1].mode;
+                self.screens = screens;
+                self.set_mode(mode);
+            }
+            Ok(_) => {}
+            Err(err) => warn!("Failed to restore the saved screens: {err}"),
+        }
+    }
+
+    /// Saves the state of the screens so that the next run starts where this one left off.
+    /// Failing to save is logged rather than preventing the application from quitting.
+    fn save_state(&mut self) {
+        let Some(path) = self.state_file.clone() else {
+            return;
+        };
+        let screens = self.screens.iter_mut().map(Screen::save_state).collect();
+        if let Err(err) = State::new(screens).save(&path) {
+            warn!("Failed to save the state to {}: {err}", path.display());
+        }
+    }
+
+    /// Switches the keybindings in effect. Pending multi-key sequences belong to the previous mode
+    /// and are discarded.
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.key_sequence.reset();
+    }
+
+    fn handle_resize<B: Backend>(&mut self, tui: &mut Tui<B>, w: u16, h: u16) -> Result<()> {
+        let area = tui.resize_viewport(w, h)?;
+        self.screen_mut().layout.resize(area);
         self.render(tui)?;
         Ok(())
     }
 
-    fn render(&mut self, tui: &mut Tui) -> Result<()> {
+    fn render<B: Backend>(&mut self, tui: &mut Tui<B>) -> Result<()> {
+        let action_tx = self.action_tx.clone();
+        let screen = self
+            .screens
+            .last_mut()
+            .expect("the screen stack is never empty");
+        let overlays = &mut self.overlays;
+        let pending_keys = self.key_sequence.to_string();
+        let pending_keys_style = self.config.theme.style("status.keys");
+        let color_depth = tui.color_depth;
         tui.draw(|frame| {
-            for component in self.components.iter_mut() {
-                if let Err(err) = component.draw(frame, frame.area()) {
-                    let _ = self
-                        .action_tx
-                        .send(Action::Error(format!("Failed to draw: {:?}", err)));
+            for (area, component) in screen.layout.components_mut() {
+                if let Err(err) = component.draw_tree(frame, area) {
+                    let _ = action_tx.send(Action::Error(format!("Failed to draw: {:?}", err)));
+                }
+            }
+            for overlay in overlays.iter_mut() {
+                if let Err(err) = overlay.draw_tree(frame, frame.area()) {
+                    let _ = action_tx.send(Action::Error(format!("Failed to draw: {:?}", err)));
                 }
             }
+            if !pending_keys.is_empty() {
+                let area = frame.area();
+                let width = (pending_keys.len() as u16 + 2).min(area.width);
+                let indicator = Rect::new(
+                    area.right() - width,
+                    area.bottom().saturating_sub(1),
+                    width,
+                    area.height.min(1),
+                );
+                frame.render_widget(
+                    Line::from(format!(" {pending_keys} ")).style(pending_keys_style),
+                    indicator,
+                );
+            }
+            color_depth.downsample_buffer(frame.buffer_mut());
         })?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Loads the configuration. In lenient mode invalid entries are skipped, and a popup that lists
+/// them is opened through `action_tx`.
+fn load_config(
+    sources: &ConfigSources,
+    lenient: bool,
+    action_tx: &mpsc::UnboundedSender<Action>,
+) -> Result<Config, ConfigError> {
+    if !lenient {
+        return Config::from_sources(sources);
+    }
+    let (config, issues) = Config::from_sources_lenient(sources)?;
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        let message = format!("Skipped invalid configuration:\n{}", issues.join("\n"));
+        let _ = action_tx.send(Action::Error(message));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::{stream, Stream, StreamExt};
+    use ratatui::{backend::TestBackend, buffer::Buffer, Frame};
+
+    use super::*;
+    use crate::layout::{LayoutConfig, LayoutNode};
+
+    /// Runs the application headless with `events` as its input, and returns the lines that were
+    /// drawn last.
+    async fn run(events: Vec<Event>) -> Result<Vec<String>> {
+        run_stream(stream::iter(events)).await
+    }
+
+    async fn run_stream(events: impl Stream<Item = Event> + Send + 'static) -> Result<Vec<String>> {
+        run_app(&mut test_app()?, events).await
+    }
+
+    /// An application with the embedded default configuration that does not save its state.
+    fn test_app() -> Result<App> {
+        let mut app = App::new(ConfigSources::defaults_only(), false, None)?;
+        app.state_file = None;
+        Ok(app)
+    }
+
+    async fn run_app(
+        app: &mut App,
+        events: impl Stream<Item = Event> + Send + 'static,
+    ) -> Result<Vec<String>> {
+        let buffer = run_app_buffer(app, events).await?;
+        Ok((0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Like [`run_app`], with the styles of the cells that were drawn last.
+    async fn run_app_buffer(
+        app: &mut App,
+        events: impl Stream<Item = Event> + Send + 'static,
+    ) -> Result<Buffer> {
+        let mut tui = Tui::with_backend(TestBackend::new(40, 8), events)?;
+        app.run_with(&mut tui).await?;
+        Ok(tui.backend().buffer().clone())
+    }
+
+    /// Runs `app` with `actions` queued before the first event, and returns what was drawn after
+    /// them.
+    async fn run_actions(app: &mut App, actions: Vec<Action>) -> Result<Buffer> {
+        for action in actions {
+            app.action_tx.send(action)?;
+        }
+        run_app_buffer(app, stream::iter([Event::Render, Event::Render])).await
+    }
+
+    /// Whether the border of the `Home` component is drawn as focused.
+    fn home_is_focused(app: &App, buffer: &Buffer) -> bool {
+        let focused = app.config.theme.style("border.focused");
+        buffer[(0, 1)].symbol() == "┌" && buffer[(0, 1)].fg == focused.fg.unwrap()
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[tokio::test]
+    async fn test_draws_home() -> Result<()> {
+        let lines = run(vec![Event::Render]).await?;
+        assert!(lines[1].starts_with("┌ Home ─"), "{lines:#?}");
+        assert!(lines[2].starts_with("│hello world "), "{lines:#?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_draws_without_render_events() -> Result<()> {
+        // nothing but a quit after a while, so every frame was requested by the application
+        let quit = stream::once(async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Event::Quit
+        });
+        let lines = run_stream(quit.boxed()).await?;
+        assert!(lines[1].starts_with("┌ Home ─"), "{lines:#?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_keys_are_cleared_when_they_time_out() -> Result<()> {
+        // `<g>` waits for a second `<g>` until the timeout
+        let app = || -> Result<App> {
+            let mut app = test_app()?;
+            let keymap = app.config.keybindings.get_mut(&Mode::Home).unwrap();
+            let keys =
+                config::parse_key_sequence("<g><g>").map_err(color_eyre::eyre::Report::msg)?;
+            keymap.insert(keys, Action::Help);
+            app.key_sequence = KeySequence::new(Duration::from_millis(50));
+            Ok(app)
+        };
+        let typed = |wait: u64| {
+            stream::iter([key(KeyCode::Char('g'))]).chain(stream::once(async move {
+                tokio::time::sleep(Duration::from_millis(wait)).await;
+                Event::Quit
+            }))
+        };
+        let lines = run_app(&mut app()?, typed(20).boxed()).await?;
+        assert!(lines[7].ends_with(" <g> "), "{lines:#?}");
+        // the ticks after the timeout expire the sequence, without any other change to draw
+        let lines = run_app(&mut app()?, typed(600).boxed()).await?;
+        assert!(lines[7].ends_with("─┘"), "{lines:#?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_help_overlay() -> Result<()> {
+        let lines = run(vec![key(KeyCode::F(1)), Event::Render]).await?;
+        assert!(
+            lines.iter().any(|line| line.contains(" Help ")),
+            "{lines:#?}"
+        );
+        Ok(())
+    }
+
+    /// A focusable component that opens the help for `q`, which the default keybindings bind to
+    /// [`Action::Quit`].
+    struct HelpOnQ;
+
+    impl Component for HelpOnQ {
+        fn is_focusable(&self) -> bool {
+            true
+        }
+
+        fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+            Ok((key.code == KeyCode::Char('q')).then_some(Action::Help))
+        }
+
+        fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_focused_component_gets_keys_before_the_keybindings() -> Result<()> {
+        let mut app = test_app()?;
+        let components =
+            HashMap::from([("home".to_string(), Box::new(HelpOnQ) as Box<dyn Component>)]);
+        app.screens[0].layout =
+            LayoutNode::build(&LayoutConfig::Component("home".to_string()), components)?;
+        run_app(&mut app, stream::iter([key(KeyCode::Char('q'))])).await?;
+        assert_eq!(
+            app.overlays.len(),
+            1,
+            "`q` quit instead of opening the help"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quit_while_an_overlay_is_open() -> Result<()> {
+        // the input never ends, so only the key can stop the application
+        let typed = |keys: Vec<Event>| stream::iter(keys).chain(stream::pending());
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        let quit = run_stream(typed(vec![key(KeyCode::F(1)), ctrl_c]));
+        assert!(tokio::time::timeout(Duration::from_secs(5), quit)
+            .await
+            .is_ok());
+
+        // `q` is typed into the search of the command palette instead
+        let ctrl_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        let search = run_stream(typed(vec![ctrl_p, key(KeyCode::Char('q'))]));
+        assert!(tokio::time::timeout(Duration::from_millis(300), search)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_then_pop_screen() -> Result<()> {
+        let mut app = test_app()?;
+        let buffer = run_actions(
+            &mut app,
+            vec![Action::PushScreen(Mode::Home), Action::PopScreen],
+        )
+        .await?;
+        assert_eq!(app.screens.len(), 1);
+        assert_eq!(app.mode, Mode::Home);
+        // pushing blurred the screen below, popping focuses it again
+        assert!(home_is_focused(&app, &buffer), "{buffer:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pop_last_screen_is_ignored() -> Result<()> {
+        let mut app = test_app()?;
+        let buffer = run_actions(&mut app, vec![Action::PopScreen]).await?;
+        assert_eq!(app.screens.len(), 1);
+        assert_eq!(app.mode, Mode::Home);
+        assert!(home_is_focused(&app, &buffer), "{buffer:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replace_screen_keeps_the_depth() -> Result<()> {
+        let mut app = test_app()?;
+        run_actions(&mut app, vec![Action::ReplaceScreen(Mode::Home)]).await?;
+        assert_eq!(app.screens.len(), 1);
+
+        let mut app = test_app()?;
+        let buffer = run_actions(
+            &mut app,
+            vec![
+                Action::PushScreen(Mode::Home),
+                Action::ReplaceScreen(Mode::Home),
+            ],
+        )
+        .await?;
+        assert_eq!(app.screens.len(), 2);
+        assert_eq!(app.mode, Mode::Home);
+        assert!(home_is_focused(&app, &buffer), "{buffer:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_applies_the_layout() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("app-reload-layout-{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let file = directory.join("config.json5");
+        std::fs::write(
+            &file,
+            r#"{ "layouts": { "Home": { "constraints": ["fill:1", "length:1"], "children": ["home", "fps"] } } }"#,
+        )?;
+        let mut app = test_app()?;
+        app.config_sources.file = Some(file);
+        let buffer = run_actions(&mut app, vec![Action::ReloadConfig]).await?;
+        std::fs::remove_dir_all(&directory)?;
+        let top: String = (0..8).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert_eq!(top, "┌ Home ─");
+        assert!(app.overlays.is_empty());
+        Ok(())
+    }
+
+    /// A state file in a directory of its own, which is removed first.
+    fn temp_state_file(name: &str) -> Result<PathBuf> {
+        let directory =
+            std::env::temp_dir().join(format!("app-state-{name}-{}", std::process::id()));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory)?;
+        }
+        Ok(directory.join("state.json"))
+    }
+
+    #[tokio::test]
+    async fn test_state_is_restored_on_the_next_start() -> Result<()> {
+        let path = temp_state_file("restore")?;
+        let mut app = test_app()?;
+        app.state_file = Some(path.clone());
+        run_actions(&mut app, vec![Action::PushScreen(Mode::Home)]).await?;
+        let saved = State::load(&path).expect("the state is saved on quit");
+        assert_eq!(saved.screens.len(), 2);
+        let focused = saved.screens[1].focused.clone();
+        assert_eq!(focused.as_deref(), Some("home"));
+
+        let mut app = test_app()?;
+        app.state_file = Some(path.clone());
+        let buffer = run_actions(&mut app, vec![]).await?;
+        assert_eq!(app.screens.len(), 2);
+        assert_eq!(app.mode, Mode::Home);
+        assert!(home_is_focused(&app, &buffer), "{buffer:?}");
+        let saved = State::load(&path).expect("the state is saved on quit");
+        assert_eq!(saved.screens[1].focused, focused);
+        // restored before `init` counted the second visit
+        for screen in &saved.screens {
+            assert_eq!(
+                screen.components["home"],
+                serde_json::json!({ "visits": 2 })
+            );
+        }
+        std::fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_state_is_saved_on_suspend() -> Result<()> {
+        let path = temp_state_file("suspend")?;
+        let mut app = test_app()?;
+        app.state_file = Some(path.clone());
+        let mut tui = Tui::with_backend(TestBackend::new(40, 8), stream::empty())?;
+        app.action_tx.send(Action::Suspend)?;
+        app.handle_actions(&mut tui)?;
+        assert!(app.should_suspend);
+        assert_eq!(State::load(&path).map(|state| state.screens.len()), Some(1));
+        std::fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quit_key_stops_before_the_end_of_input() -> Result<()> {
+        let lines = run(vec![
+            Event::Render,
+            key(KeyCode::Char('q')),
+            key(KeyCode::F(1)),
+            Event::Render,
+        ])
+        .await?;
+        assert!(!lines.iter().any(|line| line.contains(" Help ")));
         Ok(())
     }
 }
@@ -0,0 +1,281 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// Asks for the background color (OSC 11), then for the primary device attributes (DA1).
+///
+/// Virtually every terminal answers DA1, and answers in order, so a DA1 response without an
+/// OSC 11 response before it means that the terminal does not report its background color.
+const QUERY: &[u8] = b"\x1b]11;?\x1b\\\x1b[c";
+
+/// Whether the background of the terminal is light or dark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brightness {
+    Light,
+    Dark,
+}
+
+/// The background color that the terminal reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Background {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Background {
+    /// Whether the color is light or dark, judged by its luminance.
+    pub fn brightness(&self) -> Brightness {
+        let luminance =
+            0.2126 * self.red as f64 + 0.7152 * self.green as f64 + 0.0722 * self.blue as f64;
+        if luminance > 127.5 {
+            Brightness::Light
+        } else {
+            Brightness::Dark
+        }
+    }
+}
+
+/// Asks the terminal for its background color, reading the response from `input`.
+///
+/// Returns `None` if the terminal does not support the query or does not answer within
+/// `timeout`. The terminal must be in raw mode, so that the response is not echoed and can be read
+/// before a newline is typed.
+#[cfg(unix)]
+pub fn query_background(
+    input: std::os::fd::BorrowedFd,
+    output: &mut impl Write,
+    timeout: Duration,
+) -> std::io::Result<Option<Background>> {
+    use std::os::fd::AsRawFd;
+
+    output.write_all(QUERY)?;
+    output.flush()?;
+    let deadline = Instant::now() + timeout;
+    let mut response = Vec::new();
+    while !is_complete(&response) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let mut pollfd = libc::pollfd {
+            fd: input.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // rounded up, so that the loop does not end before the deadline
+        let timeout_ms = remaining
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .min(i32::MAX as u128) as i32;
+        // SAFETY: `pollfd` is a single valid `pollfd` that lives for the duration of the call.
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            -1 => return Err(std::io::Error::last_os_error()),
+            0 => continue,
+            _ => {}
+        }
+        let mut buffer = [0u8; 256];
+        // SAFETY: `buffer` is valid for writes of `buffer.len()` bytes.
+        let read =
+            unsafe { libc::read(input.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+        match read {
+            -1 => return Err(std::io::Error::last_os_error()),
+            0 => break,
+            read => response.extend_from_slice(&buffer[..read as usize]),
+        }
+    }
+    Ok(parse_response(&response))
+}
+
+/// Whether the response to [`QUERY`] is complete, i.e. the DA1 response has arrived.
+fn is_complete(response: &[u8]) -> bool {
+    let Some(start) = find(response, b"\x1b[?") else {
+        return false;
+    };
+    response[start..].contains(&b'c')
+}
+
+/// Parses the background color from an OSC 11 response like `ESC ] 11 ; rgb:ffff/ffff/ffff ST`.
+fn parse_response(response: &[u8]) -> Option<Background> {
+    let start = find(response, b"\x1b]11;")? + 5;
+    let response = &response[start..];
+    // the response ends with the same terminator as the query, BEL or ST (`ESC \`)
+    let end = response
+        .iter()
+        .position(|byte| *byte == b'\x07' || *byte == b'\x1b')?;
+    let body = std::str::from_utf8(&response[..end]).ok()?;
+    let body = body
+        .strip_prefix("rgb:")
+        .or_else(|| body.strip_prefix("rgba:"))?;
+    let mut components = body.split('/').map(parse_component);
+    Some(Background {
+        red: components.next()??,
+        green: components.next()??,
+        blue: components.next()??,
+    })
+}
+
+/// Scales a color component of 1 to 4 hex digits to 8 bits.
+fn parse_component(hex: &str) -> Option<u8> {
+    if hex.is_empty() || hex.len() > 4 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let max = (1u32 << (4 * hex.len())) - 1;
+    Some(((value * 255 + max / 2) / max) as u8)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const WHITE: Background = Background {
+        red: 255,
+        green: 255,
+        blue: 255,
+    };
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(b"\x1b]11;rgb:ffff/ffff/ffff\x1b\\\x1b[?62;c"),
+            Some(WHITE)
+        );
+        assert_eq!(
+            parse_response(b"\x1b]11;rgb:1e/1e/2e\x07"),
+            Some(Background {
+                red: 0x1e,
+                green: 0x1e,
+                blue: 0x2e,
+            })
+        );
+        assert_eq!(parse_response(b"\x1b[?62;c"), None);
+        assert_eq!(parse_response(b"\x1b]11;rgb:ffff/ffff\x07"), None);
+        assert_eq!(parse_response(b"\x1b]11;rgb:ffff/ffff/ffff"), None);
+    }
+
+    #[test]
+    fn test_parse_component() {
+        assert_eq!(parse_component("f"), Some(255));
+        assert_eq!(parse_component("80"), Some(128));
+        assert_eq!(parse_component("8000"), Some(128));
+        assert_eq!(parse_component("fffff"), None);
+        assert_eq!(parse_component("zz"), None);
+    }
+
+    #[test]
+    fn test_brightness() {
+        assert_eq!(WHITE.brightness(), Brightness::Light);
+        let solarized_dark = Background {
+            red: 0x00,
+            green: 0x2b,
+            blue: 0x36,
+        };
+        assert_eq!(solarized_dark.brightness(), Brightness::Dark);
+    }
+
+    #[cfg(unix)]
+    mod pty {
+        use std::{
+            fs::File,
+            io::{Read, Write},
+            os::fd::{AsFd, FromRawFd},
+            thread,
+        };
+
+        use pretty_assertions::assert_eq;
+
+        use super::*;
+
+        /// Opens a pseudo-terminal in raw mode. The first file is the terminal side, which
+        /// answers the queries, and the second one is the application side.
+        fn open_pty() -> (File, File) {
+            let (mut terminal, mut application) = (0, 0);
+            // SAFETY: the pointers are valid, and the name and settings are optional.
+            let result = unsafe {
+                libc::openpty(
+                    &mut terminal,
+                    &mut application,
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                )
+            };
+            assert_eq!(result, 0, "openpty failed");
+            // SAFETY: `termios` is initialized by `tcgetattr` before it is used.
+            unsafe {
+                let mut termios = std::mem::zeroed();
+                libc::tcgetattr(application, &mut termios);
+                libc::cfmakeraw(&mut termios);
+                libc::tcsetattr(application, libc::TCSANOW, &termios);
+            }
+            // SAFETY: `openpty` returned two open file descriptors that nothing else owns.
+            unsafe { (File::from_raw_fd(terminal), File::from_raw_fd(application)) }
+        }
+
+        /// Runs a fake terminal that reads the query and answers with `response`. The terminal is
+        /// returned along with the query, so that it does not hang up before the response is read.
+        fn answer(
+            mut terminal: File,
+            response: &'static [u8],
+        ) -> thread::JoinHandle<(Vec<u8>, File)> {
+            thread::spawn(move || {
+                let mut query = vec![0; QUERY.len()];
+                terminal.read_exact(&mut query).unwrap();
+                terminal.write_all(response).unwrap();
+                (query, terminal)
+            })
+        }
+
+        #[test]
+        fn test_terminal_answers() -> std::io::Result<()> {
+            let (terminal, mut application) = open_pty();
+            let handle = answer(terminal, b"\x1b]11;rgb:ffff/ffff/ffff\x07\x1b[?62;22c");
+            let background = query_background(
+                application.try_clone()?.as_fd(),
+                &mut application,
+                Duration::from_secs(5),
+            )?;
+            assert_eq!(handle.join().unwrap().0, QUERY);
+            assert_eq!(background, Some(WHITE));
+            Ok(())
+        }
+
+        #[test]
+        fn test_terminal_without_osc_11() -> std::io::Result<()> {
+            let (terminal, mut application) = open_pty();
+            let handle = answer(terminal, b"\x1b[?1;2c");
+            let start = Instant::now();
+            let background = query_background(
+                application.try_clone()?.as_fd(),
+                &mut application,
+                Duration::from_secs(5),
+            )?;
+            handle.join().unwrap();
+            assert_eq!(background, None);
+            assert!(start.elapsed() < Duration::from_secs(5));
+            Ok(())
+        }
+
+        #[test]
+        fn test_terminal_does_not_answer() -> std::io::Result<()> {
+            let (_terminal, mut application) = open_pty();
+            let timeout = Duration::from_millis(50);
+            let start = Instant::now();
+            let background =
+                query_background(application.try_clone()?.as_fd(), &mut application, timeout)?;
+            assert_eq!(background, None);
+            assert!(start.elapsed() >= timeout);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::bail, Result};
+
+use crate::{
+    cli::{Command, ConfigCommand, ConfigFormat},
+    config::{
+        config_files_in, get_config_dir, get_data_dir, Config, ConfigError, ConfigSources, CONFIG,
+    },
+    logging::LOG_FILE,
+};
+
+/// Runs a subcommand instead of the application, with the configuration read from `sources`.
+pub fn run(command: Command, sources: ConfigSources) -> Result<()> {
+    match command {
+        Command::Config(ConfigCommand::Init { force }) => init_config(force),
+        Command::Config(ConfigCommand::Path) => print_paths(&sources),
+        Command::Config(ConfigCommand::Check { path }) => check_config(ConfigSources {
+            file: path.or(sources.file),
+            ..sources
+        }),
+        Command::Config(ConfigCommand::Show { format }) => show_config(&sources, format),
+    }
+}
+
+/// Writes the default configuration to `config.json5` in the user config directory.
+fn init_config(force: bool) -> Result<()> {
+    let path = write_default_config(&get_config_dir(), force)?;
+    println!("Wrote the default configuration to {}", path.display());
+    Ok(())
+}
+
+/// Writes the default configuration to `config.json5` in `config_dir`. With `force`, the
+/// configuration files that are already there are renamed to `<file>.bak`, so that they are no
+/// longer layered on top of the new file.
+fn write_default_config(config_dir: &Path, force: bool) -> Result<PathBuf> {
+    let existing = config_files_in(config_dir);
+    if !force && !existing.is_empty() {
+        bail!(
+            "{} already exists. Pass --force to replace it",
+            existing[0].display()
+        );
+    }
+    for file in existing {
+        let mut backup = file.clone().into_os_string();
+        backup.push(".bak");
+        fs::rename(&file, &backup)?;
+        println!(
+            "Moved {} to {}",
+            file.display(),
+            Path::new(&backup).display()
+        );
+    }
+    fs::create_dir_all(config_dir)?;
+    let path = config_dir.join("config.json5");
+    fs::write(&path, CONFIG)?;
+    Ok(path)
+}
+
+fn print_paths(sources: &ConfigSources) -> Result<()> {
+    println!("Config directories (lowest precedence first):");
+    for dir in sources.config_dirs() {
+        println!("  {}", dir.display());
+    }
+    println!("Data directory: {}", get_data_dir().display());
+    println!(
+        "Log file: {}",
+        get_data_dir().join(LOG_FILE.clone()).display()
+    );
+    let files = sources.files();
+    if files.is_empty() {
+        println!("Config files: none, the defaults are used");
+    } else {
+        println!("Config files:");
+        for file in files {
+            println!("  {}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Loads the configuration like the application does and reports every problem with it. Returns
+/// an error if there is any, so that the command exits with a non-zero status in CI.
+fn check_config(sources: ConfigSources) -> Result<()> {
+    let checked = sources.files();
+    let issues = match Config::from_sources_lenient(&sources) {
+        Ok((config, issues)) => {
+            for conflict in &config.key_conflicts {
+                eprintln!("warning: {conflict}");
+            }
+            issues
+        }
+        Err(ConfigError { issues }) => issues,
+    };
+    if !issues.is_empty() {
+        let count = issues.len();
+        eprintln!("{}", ConfigError { issues });
+        bail!("The configuration has {count} invalid entries");
+    }
+    if checked.is_empty() {
+        println!("No configuration files, the defaults are valid");
+    }
+    for file in checked {
+        println!("{}: ok", file.display());
+    }
+    Ok(())
+}
+
+/// Prints the merged configuration. Invalid entries are reported on stderr and left out, like
+/// the application does with `--lenient`.
+fn show_config(sources: &ConfigSources, format: ConfigFormat) -> Result<()> {
+    let (config, issues) = Config::from_sources_lenient(sources)?;
+    for issue in issues {
+        eprintln!("Skipped invalid configuration: {issue}");
+    }
+    println!("{}", format_config(&config, format)?);
+    Ok(())
+}
+
+fn format_config(config: &Config, format: ConfigFormat) -> Result<String> {
+    let value = config.to_value()?;
+    let text = match format {
+        // JSON is valid JSON5
+        ConfigFormat::Json5 => serde_json::to_string_pretty(&value)?,
+        ConfigFormat::Toml => toml::to_string_pretty(&value)?,
+        ConfigFormat::Yaml => serde_norway::to_string(&value)?,
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_init_config_force() -> Result<()> {
+        let dir = env::temp_dir().join(format!("config-init-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("config.toml"), "tick_rate = 10.0")?;
+        assert!(write_default_config(&dir, false).is_err());
+        assert!(!dir.join("config.json5").exists());
+
+        let path = write_default_config(&dir, true)?;
+        assert_eq!(fs::read_to_string(&path)?, CONFIG);
+        // the old file is kept aside instead of being layered on top of the new one
+        assert_eq!(config_files_in(&dir), [path]);
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak"))?,
+            "tick_rate = 10.0"
+        );
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_config() -> Result<()> {
+        let dir = env::temp_dir().join(format!("config-check-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let sources = |file: &str| ConfigSources {
+            file: Some(dir.join(file)),
+            environment: Some(HashMap::new()),
+            ..Default::default()
+        };
+        fs::write(dir.join("good.json5"), "{ tick_rate: 10 }")?;
+        fs::write(
+            dir.join("bad.json5"),
+            r#"{ keybindings: { Home: { "<q>": "Jump" } } }"#,
+        )?;
+        let good = check_config(sources("good.json5"));
+        let bad = check_config(sources("bad.json5"));
+        fs::remove_dir_all(&dir)?;
+        assert!(good.is_ok(), "{good:?}");
+        assert_eq!(
+            bad.unwrap_err().to_string(),
+            "The configuration has 1 invalid entries"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_formatted_config_reads_back() -> Result<()> {
+        let (defaults, _) = Config::parse(CONFIG, config::FileFormat::Json5)?;
+        for (format, file_format) in [
+            (ConfigFormat::Json5, config::FileFormat::Json5),
+            (ConfigFormat::Yaml, config::FileFormat::Yaml),
+            (ConfigFormat::Toml, config::FileFormat::Toml),
+        ] {
+            let text = format_config(&defaults, format)?;
+            let (config, issues) = Config::parse(&text, file_format)?;
+            assert_eq!(issues, vec![], "{format:?}");
+            assert_eq!(config.to_value()?, defaults.to_value()?, "{format:?}");
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,15 @@
 use color_eyre::Result;
 use crossterm::event::{KeyEvent, MouseEvent};
-use ratatui::{
-    layout::{Rect, Size},
-    Frame,
-};
+use ratatui::{layout::Rect, Frame};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{action::Action, config::Config, tui::Event};
 
+pub mod dialog;
 pub mod fps;
+pub mod help;
 pub mod home;
+pub mod palette;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 ///
@@ -46,15 +46,69 @@ pub trait Component {
     ///
     /// # Arguments
     ///
-    /// * `area` - Rectangular area to initialize the component within.
+    /// * `area` - Rectangular area that the layout assigned to the component.
     ///
     /// # Returns
     ///
     /// * `Result<()>` - An Ok result or an error.
-    fn init(&mut self, area: Size) -> Result<()> {
+    fn init(&mut self, area: Rect) -> Result<()> {
         let _ = area; // to appease clippy
         Ok(())
     }
+    /// The state to keep across restarts, such as a scroll position or the selected tab.
+    ///
+    /// The state is saved to the data directory when the application quits or is suspended and
+    /// passed to [`Component::restore_state`] on the next start. Only the components of the
+    /// layout are asked, a container saves the state of its children with its own.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<serde_json::Value>` - The state to save or none if there is nothing to keep.
+    fn save_state(&self) -> Option<serde_json::Value> {
+        None
+    }
+    /// Restore the state returned by [`Component::save_state`] in a previous run.
+    ///
+    /// Called before [`Component::init`]. Returning an error keeps the component in its initial
+    /// state, e.g. when the state was saved by an older version of the component.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The state that was saved.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An Ok result or an error.
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let _ = state; // to appease clippy
+        Ok(())
+    }
+    /// Whether the component can receive focus.
+    ///
+    /// Only focusable components take part in focus cycling and receive key events.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the component can be focused.
+    fn is_focusable(&self) -> bool {
+        false
+    }
+    /// Called when the component gains focus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An Ok result or an error.
+    fn focus(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called when the component loses focus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An Ok result or an error.
+    fn blur(&mut self) -> Result<()> {
+        Ok(())
+    }
     /// Handle incoming events and produce actions if necessary.
     ///
     /// # Arguments
@@ -74,6 +128,11 @@ pub trait Component {
     }
     /// Handle key events and produce actions if necessary.
     ///
+    /// Key events are only sent to the focused component. Returning an action marks the key as
+    /// handled, otherwise it bubbles up to the application keybindings. Key releases are not sent
+    /// to components, and repeats are only reported as such with the `keyboard_enhancement`
+    /// configuration.
+    ///
     /// # Arguments
     ///
     /// * `key` - A key event to be processed.
@@ -111,6 +170,45 @@ pub trait Component {
         let _ = action; // to appease clippy
         Ok(None)
     }
+    /// Whether the component changed since it was last drawn.
+    ///
+    /// Frames are only drawn when something changed. Input and the actions of the application
+    /// always lead to a new frame, so this only has to report the changes that a component makes
+    /// on its own, e.g. on [`Action::Tick`]. Set a flag in [`Component::update`] and clear it in
+    /// [`Component::draw`]. An animation keeps returning `true` while it runs, which draws it at
+    /// the frame rate.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the application should draw a new frame.
+    fn is_dirty(&self) -> bool {
+        false
+    }
+    /// The child components owned by this component.
+    ///
+    /// Children receive the same plumbing as their parent through [`ComponentTree`], so a
+    /// container only has to list them here instead of forwarding every call by hand.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<&mut dyn Component>` - The children, in event dispatch and drawing order.
+    fn children(&mut self) -> Vec<&mut dyn Component> {
+        Vec::new()
+    }
+    /// Split the area of the component between its children.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area in which the component is drawn.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Rect>` - One area per child, in the same order as [`Component::children`]. Children
+    ///   without a matching area are given the whole area of the component.
+    fn child_areas(&self, area: Rect) -> Vec<Rect> {
+        let _ = area; // to appease clippy
+        Vec::new()
+    }
     /// Render the component on the screen. (REQUIRED)
     ///
     /// # Arguments
@@ -123,3 +221,220 @@ pub trait Component {
     /// * `Result<()>` - An Ok result or an error.
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()>;
 }
+
+/// Plumbing that reaches a component and, recursively, all of its children.
+///
+/// The application drives components through these methods. Each method calls the matching
+/// [`Component`] method on the component itself and then on every child returned by
+/// [`Component::children`].
+pub trait ComponentTree: Component {
+    fn register_action_handler_tree(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.register_action_handler(tx.clone())?;
+        for child in self.children() {
+            child.register_action_handler_tree(tx.clone())?;
+        }
+        Ok(())
+    }
+
+    fn register_config_handler_tree(&mut self, config: Config) -> Result<()> {
+        self.register_config_handler(config.clone())?;
+        for child in self.children() {
+            child.register_config_handler_tree(config.clone())?;
+        }
+        Ok(())
+    }
+
+    fn init_tree(&mut self, area: Rect) -> Result<()> {
+        self.init(area)?;
+        let areas = self.child_areas(area);
+        for (index, child) in self.children().into_iter().enumerate() {
+            child.init_tree(areas.get(index).copied().unwrap_or(area))?;
+        }
+        Ok(())
+    }
+
+    /// Events are offered to the children first and bubble up to the component itself when no
+    /// child handles them.
+    fn handle_events_tree(&mut self, event: Option<Event>) -> Result<Option<Action>> {
+        for child in self.children() {
+            if let Some(action) = child.handle_events_tree(event.clone())? {
+                return Ok(Some(action));
+            }
+        }
+        self.handle_events(event)
+    }
+
+    fn update_tree(&mut self, action: Action) -> Result<Vec<Action>> {
+        let mut actions: Vec<Action> = self.update(action.clone())?.into_iter().collect();
+        for child in self.children() {
+            actions.extend(child.update_tree(action.clone())?);
+        }
+        Ok(actions)
+    }
+
+    fn is_dirty_tree(&mut self) -> bool {
+        self.is_dirty()
+            || self
+                .children()
+                .into_iter()
+                .any(|child| child.is_dirty_tree())
+    }
+
+    /// The component is drawn first so that its children are drawn on top of it.
+    fn draw_tree(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        self.draw(frame, area)?;
+        let areas = self.child_areas(area);
+        for (index, child) in self.children().into_iter().enumerate() {
+            child.draw_tree(frame, areas.get(index).copied().unwrap_or(area))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Component + ?Sized> ComponentTree for T {}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use ratatui::{
+        backend::TestBackend,
+        layout::{Constraint, Layout},
+        widgets::Paragraph,
+        Terminal,
+    };
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Label {
+        text: &'static str,
+        updates: usize,
+        dirty: bool,
+        area: Option<Rect>,
+        tx: Option<UnboundedSender<Action>>,
+    }
+
+    impl Component for Label {
+        fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+            self.tx = Some(tx);
+            Ok(())
+        }
+
+        fn init(&mut self, area: Rect) -> Result<()> {
+            self.area = Some(area);
+            Ok(())
+        }
+
+        fn handle_key_event(&mut self, _key: KeyEvent) -> Result<Option<Action>> {
+            Ok((self.text == "right").then_some(Action::Help))
+        }
+
+        fn update(&mut self, action: Action) -> Result<Option<Action>> {
+            self.updates += 1;
+            self.dirty = action == Action::Tick;
+            Ok(None)
+        }
+
+        fn is_dirty(&self) -> bool {
+            self.dirty
+        }
+
+        fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+            self.dirty = false;
+            frame.render_widget(Paragraph::new(self.text), area);
+            Ok(())
+        }
+    }
+
+    struct Split {
+        left: Label,
+        right: Label,
+    }
+
+    impl Component for Split {
+        fn handle_key_event(&mut self, _key: KeyEvent) -> Result<Option<Action>> {
+            Ok(Some(Action::Quit))
+        }
+
+        fn update(&mut self, action: Action) -> Result<Option<Action>> {
+            Ok((action == Action::Tick).then_some(Action::Render))
+        }
+
+        fn children(&mut self) -> Vec<&mut dyn Component> {
+            vec![&mut self.left, &mut self.right]
+        }
+
+        fn child_areas(&self, area: Rect) -> Vec<Rect> {
+            Layout::horizontal([Constraint::Fill(1); 2])
+                .split(area)
+                .to_vec()
+        }
+
+        fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn split() -> Split {
+        Split {
+            left: Label {
+                text: "left",
+                ..Default::default()
+            },
+            right: Label {
+                text: "right",
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_plumbing_reaches_children() -> Result<()> {
+        let mut split = split();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        split.register_action_handler_tree(tx)?;
+        split.init_tree(Rect::new(0, 0, 10, 1))?;
+        assert!(split.left.tx.is_some() && split.right.tx.is_some());
+        assert_eq!(split.left.area, Some(Rect::new(0, 0, 5, 1)));
+        assert_eq!(split.right.area, Some(Rect::new(5, 0, 5, 1)));
+
+        assert_eq!(split.update_tree(Action::Tick)?, vec![Action::Render]);
+        assert_eq!((split.left.updates, split.right.updates), (1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_bubble_up_from_children() -> Result<()> {
+        let mut split = split();
+        let key = Event::Key(KeyEvent::from(crossterm::event::KeyCode::Enter));
+        assert_eq!(
+            split.handle_events_tree(Some(key.clone()))?,
+            Some(Action::Help)
+        );
+        split.right.text = "other";
+        assert_eq!(split.handle_events_tree(Some(key))?, Some(Action::Quit));
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_children_into_their_areas() -> Result<()> {
+        let mut split = split();
+        let mut terminal = Terminal::new(TestBackend::new(10, 1))?;
+        terminal.draw(|frame| split.draw_tree(frame, frame.area()).unwrap())?;
+        terminal.backend().assert_buffer_lines(["left right"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dirty_children() -> Result<()> {
+        let mut split = split();
+        assert!(!split.is_dirty_tree());
+        split.right.update_tree(Action::Tick)?;
+        assert!(split.is_dirty_tree());
+        let mut terminal = Terminal::new(TestBackend::new(10, 1))?;
+        terminal.draw(|frame| split.draw_tree(frame, frame.area()).unwrap())?;
+        assert!(!split.is_dirty_tree());
+        Ok(())
+    }
+}
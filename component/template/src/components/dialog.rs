@@ -0,0 +1,179 @@
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Wrap},
+    Frame,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::Component;
+use crate::{action::Action, config::Config, theme::Theme};
+
+/// The area of a popup of the given size, centered in `area`.
+pub fn centered_area(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+/// Draws a bordered, cleared popup with the given text centered in `area`.
+fn draw_popup(frame: &mut Frame, area: Rect, block: Block, text: Vec<Line>) {
+    let width = (area.width * 3 / 5).max(20);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let lines: usize = text
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(inner_width))
+        .sum();
+    let popup = centered_area(area, width, lines as u16 + 2);
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .centered(),
+        popup,
+    );
+}
+
+/// Popup that shows the message of an [`Action::Error`] until it is dismissed.
+pub struct ErrorDialog {
+    message: String,
+    theme: Theme,
+}
+
+impl ErrorDialog {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Component for ErrorDialog {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => Ok(Some(Action::CloseOverlay)),
+            _ => Ok(None),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let block = Block::bordered()
+            .title(" Error ")
+            .border_style(self.theme.style("status.error"));
+        let mut text: Vec<Line> = self.message.lines().map(Line::from).collect();
+        text.push(Line::from(""));
+        text.push(Line::from("Press <enter> to dismiss").style(self.theme.style("text.dim")));
+        draw_popup(frame, area, block, text);
+        Ok(())
+    }
+}
+
+/// Popup that asks the user to confirm an action before it is sent.
+pub struct ConfirmDialog {
+    message: String,
+    action: Action,
+    command_tx: Option<UnboundedSender<Action>>,
+    theme: Theme,
+}
+
+impl ConfirmDialog {
+    pub fn new(message: String, action: Action) -> Self {
+        Self {
+            message,
+            action,
+            command_tx: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Component for ConfirmDialog {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                if let Some(tx) = &self.command_tx {
+                    // close the dialog first so that the action can open its own popups
+                    tx.send(Action::CloseOverlay)?;
+                    tx.send(self.action.clone())?;
+                }
+                Ok(None)
+            }
+            KeyCode::Char('n') | KeyCode::Esc => Ok(Some(Action::CloseOverlay)),
+            _ => Ok(None),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let block = Block::bordered()
+            .title(" Confirm ")
+            .border_style(self.theme.style("border.focused"));
+        let text = vec![
+            Line::from(self.message.as_str()),
+            Line::from(""),
+            Line::from("<y> yes, <n> no").style(self.theme.style("text.dim")),
+        ];
+        draw_popup(frame, area, block, text);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn test_centered_area() {
+        let area = centered_area(Rect::new(0, 0, 20, 10), 10, 4);
+        assert_eq!(area, Rect::new(5, 3, 10, 4));
+    }
+
+    #[test]
+    fn test_confirm_sends_action() -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut dialog = ConfirmDialog::new("Quit?".to_string(), Action::Quit);
+        dialog.register_action_handler(tx)?;
+        assert_eq!(dialog.handle_key_event(KeyCode::Char('y').into())?, None);
+        assert_eq!(rx.try_recv().ok(), Some(Action::CloseOverlay));
+        assert_eq!(rx.try_recv().ok(), Some(Action::Quit));
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirm_cancel() -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut dialog = ConfirmDialog::new("Quit?".to_string(), Action::Quit);
+        dialog.register_action_handler(tx)?;
+        assert_eq!(
+            dialog.handle_key_event(KeyCode::Esc.into())?,
+            Some(Action::CloseOverlay)
+        );
+        assert!(rx.try_recv().is_err());
+        Ok(())
+    }
+}
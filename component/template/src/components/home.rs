@@ -1,5 +1,6 @@
 use color_eyre::Result;
 use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::Component;
@@ -9,6 +10,15 @@ use crate::{action::Action, config::Config};
 pub struct Home {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    focused: bool,
+    state: HomeState,
+}
+
+/// What `Home` keeps across restarts, see [`Component::save_state`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct HomeState {
+    /// How many times the screen was opened.
+    visits: u32,
 }
 
 impl Home {
@@ -28,6 +38,34 @@ impl Component for Home {
         Ok(())
     }
 
+    fn init(&mut self, _area: Rect) -> Result<()> {
+        self.state.visits += 1;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.state).ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.state = serde_json::from_value(state)?;
+        Ok(())
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn focus(&mut self) -> Result<()> {
+        self.focused = true;
+        Ok(())
+    }
+
+    fn blur(&mut self) -> Result<()> {
+        self.focused = false;
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         match action {
             Action::Tick => {
@@ -42,7 +80,70 @@ impl Component for Home {
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
-        frame.render_widget(Paragraph::new("hello world"), area);
+        let theme = &self.config.theme;
+        let border = if self.focused {
+            "border.focused"
+        } else {
+            "border"
+        };
+        let block = Block::bordered()
+            .title(" Home ")
+            .border_style(theme.style(border));
+        let paragraph = Paragraph::new("hello world")
+            .style(theme.style("text"))
+            .block(block);
+        frame.render_widget(paragraph, area);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{config::CONFIG, snapshot::assert_snapshot, theme::Theme};
+
+    fn config() -> Result<Config> {
+        let (mut config, _) = Config::parse(CONFIG, config::FileFormat::Json5)?;
+        config.theme =
+            Theme::resolve("dark", &config.themes).map_err(color_eyre::eyre::Report::msg)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_draw() -> Result<()> {
+        let mut home = Home::new();
+        home.register_config_handler(config()?)?;
+        assert_snapshot("home", 20, 4, |frame| {
+            home.draw(frame, frame.area()).unwrap();
+        });
+        home.focus()?;
+        assert_snapshot("home_focused", 20, 4, |frame| {
+            home.draw(frame, frame.area()).unwrap();
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_round_trip() -> Result<()> {
+        let mut home = Home::new();
+        home.init(Rect::default())?;
+        let state = home.save_state();
+        assert_eq!(state, Some(json!({ "visits": 1 })));
+
+        let mut restored = Home::new();
+        restored.restore_state(state.unwrap())?;
+        restored.init(Rect::default())?;
+        assert_eq!(restored.save_state(), Some(json!({ "visits": 2 })));
         Ok(())
     }
+
+    #[test]
+    fn test_invalid_state_is_rejected() {
+        let mut home = Home::new();
+        assert!(home.restore_state(json!({ "visits": "many" })).is_err());
+        assert_eq!(home.state, HomeState::default());
+    }
 }
@@ -1,36 +1,249 @@
 #![allow(dead_code)] // Remove this once you start using the code
 
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use color_eyre::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use derive_deref::{Deref, DerefMut};
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache,
+};
 use ratatui::style::{Color, Modifier, Style};
-use serde::{de::Deserializer, Deserialize};
-use tracing::error;
-
-use crate::{action::Action, app::Mode};
-
-const CONFIG: &str = include_str!("../.config/config.json5");
-
-#[derive(Clone, Debug, Deserialize, Default)]
+use serde::{de::Deserializer, Deserialize, Serialize, Serializer};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, warn};
+
+use crate::{
+    action::Action,
+    app::Mode,
+    keymap::{find_conflicts, ConflictKind, KeyConflict},
+    layout::LayoutConfig,
+    theme::{Theme, ThemeConfig, AUTO_THEME, DEFAULT_THEME},
+};
+
+pub const CONFIG: &str = include_str!("../.config/config.json5");
+
+/// The configuration files that are read from the config directory, in order of precedence.
+const CONFIG_FILES: [(&str, config::FileFormat); 5] = [
+    ("config.json5", config::FileFormat::Json5),
+    ("config.json", config::FileFormat::Json),
+    ("config.yaml", config::FileFormat::Yaml),
+    ("config.toml", config::FileFormat::Toml),
+    ("config.ini", config::FileFormat::Ini),
+];
+
+/// The number of ticks per second when the configuration does not set `tick_rate`.
+const DEFAULT_TICK_RATE: f64 = 4.0;
+
+/// The maximum number of frames per second when the configuration does not set `frame_rate`.
+const DEFAULT_FRAME_RATE: f64 = 60.0;
+
+/// How long the config directory must be quiet before a change is reloaded, so that an editor
+/// saving a file in several steps triggers a single reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
     pub data_dir: PathBuf,
     #[serde(default)]
     pub config_dir: PathBuf,
+    /// Milliseconds to wait for the next key of a multi-key binding.
+    #[serde(default)]
+    pub key_sequence_timeout: u64,
+    /// The name of the theme in use.
+    #[serde(default)]
+    pub theme: String,
+    /// Number of ticks per second.
+    #[serde(default)]
+    pub tick_rate: f64,
+    /// Maximum number of frames per second. Frames are only drawn when something changed.
+    #[serde(default)]
+    pub frame_rate: f64,
+    /// Use the kitty keyboard protocol in terminals that support it, for key release and repeat
+    /// events and keys that are ambiguous otherwise, like `<Ctrl-i>` and `<Tab>`.
+    #[serde(default)]
+    pub keyboard_enhancement: bool,
+}
+
+/// Where the configuration is read from besides the embedded defaults, and the values that
+/// override it.
+///
+/// From the lowest precedence to the highest, the configuration is made of the defaults, the
+/// files in the system, user and project config directories (or only [`ConfigSources::file`]),
+/// the `<NAME>__SECTION__KEY` environment variables, and the command line flags.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// A file that is read instead of the files in the config directories.
+    pub file: Option<PathBuf>,
+    /// The directories to read the files from instead of [`config_dirs`].
+    pub dirs: Option<Vec<PathBuf>>,
+    /// Overrides `tick_rate`, e.g. from the command line.
+    pub tick_rate: Option<f64>,
+    /// Overrides `frame_rate`, e.g. from the command line.
+    pub frame_rate: Option<f64>,
+    /// The `<NAME>__SECTION__KEY` variables to read instead of the environment of the process.
+    pub environment: Option<HashMap<String, String>>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+impl ConfigSources {
+    /// Only the defaults embedded in the application, without any file or environment variable,
+    /// so that tests do not depend on the configuration of the machine they run on.
+    pub fn defaults_only() -> Self {
+        Self {
+            dirs: Some(Vec::new()),
+            environment: Some(HashMap::new()),
+            ..Default::default()
+        }
+    }
+
+    /// The directories that configuration files are read from, from the lowest precedence to the
+    /// highest.
+    pub fn config_dirs(&self) -> Vec<PathBuf> {
+        self.dirs.clone().unwrap_or_else(config_dirs)
+    }
+
+    /// The configuration files that are read, from the lowest precedence to the highest. An
+    /// explicit [`ConfigSources::file`] is returned even if it does not exist, so that loading
+    /// it fails.
+    pub fn files(&self) -> Vec<PathBuf> {
+        match &self.file {
+            Some(file) => vec![file.clone()],
+            None => self
+                .config_dirs()
+                .iter()
+                .flat_map(|dir| config_files_in(dir))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Config {
-    #[serde(default, flatten)]
     pub config: AppConfig,
-    #[serde(default)]
     pub keybindings: KeyBindings,
-    #[serde(default)]
-    pub styles: Styles,
+    /// Every theme that is defined, by name.
+    pub themes: HashMap<String, ThemeConfig>,
+    /// The theme selected by `config.theme`, which components take their styles from.
+    pub theme: Theme,
+    pub layouts: HashMap<Mode, LayoutConfig>,
+    /// Descriptions of actions shown in the help popup, keyed by action name.
+    pub descriptions: HashMap<String, String>,
+    /// Keybindings that shadow each other or cannot be sent. They are kept, but logged as
+    /// warnings.
+    pub key_conflicts: Vec<KeyConflict>,
+}
+
+/// A problem with the configuration, and where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// The file that contains the problem, if it is known.
+    pub file: Option<PathBuf>,
+    /// The section of the configuration, e.g. `keybindings`.
+    pub section: Option<String>,
+    /// The mode, or the theme for entries of the `themes` section.
+    pub mode: Option<String>,
+    /// The key of the entry within the mode, e.g. `<ctrl-d>`.
+    pub key: Option<String>,
+    pub reason: String,
+}
+
+impl ConfigIssue {
+    fn new(section: &str, reason: impl ToString) -> Self {
+        Self {
+            file: None,
+            section: Some(section.to_string()),
+            mode: None,
+            key: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Sets the file to the one that `value` was read from.
+    fn at(mut self, value: &config::Value) -> Self {
+        self.file = origin(value);
+        self
+    }
+
+    fn mode(mut self, mode: &str) -> Self {
+        self.mode = Some(mode.to_string());
+        self
+    }
+
+    fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        let path: Vec<&str> = [&self.section, &self.mode, &self.key]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !path.is_empty() {
+            write!(f, "{}: ", path.join("."))?;
+        }
+        write!(f, "{}", self.reason)
+    }
+}
+
+/// Every problem that was found while loading the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.issues.as_slice() {
+            [issue] => write!(f, "Invalid configuration: {issue}"),
+            issues => {
+                write!(f, "{} problems in the configuration:", issues.len())?;
+                for issue in issues {
+                    write!(f, "\n  {issue}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<config::ConfigError> for ConfigError {
+    /// Errors that prevent the configuration from being read at all, e.g. a syntax error.
+    fn from(err: config::ConfigError) -> Self {
+        let (file, reason) = match err {
+            config::ConfigError::FileParse { uri, cause } => {
+                (uri.map(PathBuf::from), cause.to_string())
+            }
+            err => (None, err.to_string()),
+        };
+        Self {
+            issues: vec![ConfigIssue {
+                file,
+                section: None,
+                mode: None,
+                key: None,
+                reason,
+            }],
+        }
+    }
 }
 
 lazy_static! {
@@ -46,36 +259,128 @@ lazy_static! {
 }
 
 impl Config {
-    pub fn new() -> Result<Self, config::ConfigError> {
-        let default_config: Config = json5::from_str(CONFIG).unwrap();
+    /// Loads the configuration. If any entry is invalid, all of the problems are returned.
+    pub fn new() -> Result<Self, ConfigError> {
+        Self::from_sources(&ConfigSources::default())
+    }
+
+    /// Loads the configuration from `sources`, like [`Config::new`].
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let (config, _) = Self::load(false, sources)?;
+        Ok(config)
+    }
+
+    /// Loads the configuration from `sources`, skipping invalid entries instead of failing. The
+    /// skipped entries are logged and returned so that they can be shown to the user.
+    ///
+    /// Files that cannot be parsed at all are still an error.
+    pub fn from_sources_lenient(
+        sources: &ConfigSources,
+    ) -> Result<(Self, Vec<ConfigIssue>), ConfigError> {
+        Self::load(true, sources)
+    }
+
+    /// The description of the action named `name` from the `descriptions` section. Names are
+    /// matched regardless of case, as keys are lowercased when the files are read.
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.descriptions
+            .iter()
+            .find(|(action, _)| action.eq_ignore_ascii_case(name))
+            .map(|(_, description)| description.as_str())
+    }
+
+    /// Reads a configuration from `text` alone, without the defaults or the configuration files.
+    /// Invalid entries are returned as issues.
+    pub fn parse(
+        text: &str,
+        format: config::FileFormat,
+    ) -> Result<(Self, Vec<ConfigIssue>), ConfigError> {
+        let mut issues = Vec::new();
+        let source = config::Config::builder()
+            .add_source(config::File::from_str(text, format))
+            .build()?;
+        let config = Self::from_source(&source, &mut issues)?;
+        Ok((config, issues))
+    }
+
+    /// The configuration in the layout of the configuration files, so that it can be written out
+    /// and read back.
+    pub fn to_value(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(&self.config)?;
+        let layouts: BTreeMap<String, &LayoutConfig> = self
+            .layouts
+            .iter()
+            .map(|(mode, layout)| (format!("{mode:?}"), layout))
+            .collect();
+        let sections = [
+            ("keybindings", serde_json::to_value(&self.keybindings)?),
+            (
+                "themes",
+                serde_json::to_value(self.themes.iter().collect::<BTreeMap<_, _>>())?,
+            ),
+            ("layouts", serde_json::to_value(layouts)?),
+            (
+                "descriptions",
+                serde_json::to_value(self.descriptions.iter().collect::<BTreeMap<_, _>>())?,
+            ),
+        ];
+        if let serde_json::Value::Object(map) = &mut value {
+            map.extend(sections.map(|(name, section)| (name.to_string(), section)));
+        }
+        Ok(value)
+    }
+
+    fn load(
+        lenient: bool,
+        sources: &ConfigSources,
+    ) -> Result<(Self, Vec<ConfigIssue>), ConfigError> {
+        let (default_config, mut issues) = Self::parse(CONFIG, config::FileFormat::Json5)?;
         let data_dir = get_data_dir();
         let config_dir = get_config_dir();
         let mut builder = config::Config::builder()
             .set_default("data_dir", data_dir.to_str().unwrap())?
-            .set_default("config_dir", config_dir.to_str().unwrap())?;
-
-        let config_files = [
-            ("config.json5", config::FileFormat::Json5),
-            ("config.json", config::FileFormat::Json),
-            ("config.yaml", config::FileFormat::Yaml),
-            ("config.toml", config::FileFormat::Toml),
-            ("config.ini", config::FileFormat::Ini),
-        ];
-        let mut found_config = false;
-        for (file, format) in &config_files {
-            let source = config::File::from(config_dir.join(file))
-                .format(*format)
-                .required(false);
-            builder = builder.add_source(source);
-            if config_dir.join(file).exists() {
-                found_config = true
-            }
+            .set_default("config_dir", config_dir.to_str().unwrap())?
+            .set_default("key_sequence_timeout", 1000)?
+            .set_default("theme", AUTO_THEME)?
+            .set_default("tick_rate", DEFAULT_TICK_RATE)?
+            .set_default("frame_rate", DEFAULT_FRAME_RATE)?;
+
+        let files = sources.files();
+        if files.is_empty() && sources.dirs.is_none() {
+            error!(
+                "No configuration file found in {}. Run `{} config init` to create one",
+                config_dir.display(),
+                env!("CARGO_PKG_NAME")
+            );
         }
-        if !found_config {
-            error!("No configuration file found. Application may not behave as expected");
+        for file in &files {
+            builder = builder.add_source(config::File::from(file.as_path()));
+        }
+        builder = builder
+            .add_source(
+                config::Environment::with_prefix(&PROJECT_NAME)
+                    .prefix_separator("__")
+                    .separator("__")
+                    .try_parsing(true)
+                    .source(sources.environment.clone()),
+            )
+            .set_override_option("tick_rate", sources.tick_rate)?
+            .set_override_option("frame_rate", sources.frame_rate)?;
+
+        let mut cfg = Self::from_source(&builder.build()?, &mut issues)?;
+        for (key, rate, default) in [
+            ("tick_rate", &mut cfg.config.tick_rate, DEFAULT_TICK_RATE),
+            ("frame_rate", &mut cfg.config.frame_rate, DEFAULT_FRAME_RATE),
+        ] {
+            // a rate of zero would make the interval between ticks or frames infinite
+            if !(*rate > 0.0 && rate.is_finite()) {
+                issues.push(ConfigIssue::new(
+                    key,
+                    format!("Must be positive, not {rate}"),
+                ));
+                *rate = default;
+            }
         }
-
-        let mut cfg: Self = builder.build()?.try_deserialize()?;
 
         for (mode, default_bindings) in default_config.keybindings.iter() {
             let user_bindings = cfg.keybindings.entry(*mode).or_default();
@@ -85,15 +390,223 @@ impl Config {
                     .or_insert_with(|| cmd.clone());
             }
         }
-        for (mode, default_styles) in default_config.styles.iter() {
-            let user_styles = cfg.styles.entry(*mode).or_default();
-            for (style_key, style) in default_styles.iter() {
-                user_styles.entry(style_key.clone()).or_insert(*style);
+        for (name, default_theme) in default_config.themes.iter() {
+            let user_theme = cfg.themes.entry(name.clone()).or_default();
+            if user_theme.extends.is_none() {
+                user_theme.extends = default_theme.extends.clone();
             }
+            for (style_key, style) in default_theme.styles.iter() {
+                user_theme.styles.entry(style_key.clone()).or_insert(*style);
+            }
+        }
+        for (mode, default_layout) in default_config.layouts.iter() {
+            cfg.layouts
+                .entry(*mode)
+                .or_insert_with(|| default_layout.clone());
         }
+        for (action, description) in default_config.descriptions.iter() {
+            cfg.descriptions
+                .entry(action.clone())
+                .or_insert_with(|| description.clone());
+        }
+        cfg.key_conflicts.extend(find_conflicts(
+            &cfg.keybindings,
+            &default_config.keybindings,
+            cfg.config.keyboard_enhancement,
+        ));
+        for conflict in &cfg.key_conflicts {
+            warn!("Keybinding conflict: {conflict}");
+        }
+
+        let mut names: Vec<&String> = cfg.themes.keys().collect();
+        names.sort();
+        for name in names {
+            if let Err(reason) = Theme::resolve(name, &cfg.themes) {
+                issues.push(ConfigIssue::new("themes", reason).mode(name));
+            }
+        }
+        cfg.theme = match Theme::select(&cfg.config.theme, &cfg.themes, None) {
+            Ok(theme) => theme,
+            Err(reason) => {
+                issues.push(ConfigIssue::new("theme", reason));
+                Theme::resolve(DEFAULT_THEME, &cfg.themes).unwrap_or_default()
+            }
+        };
+
+        if !lenient && !issues.is_empty() {
+            return Err(ConfigError { issues });
+        }
+        for issue in &issues {
+            warn!("Skipping invalid configuration: {issue}");
+        }
+        Ok((cfg, issues))
+    }
+
+    /// Reads the configuration from `source`. Invalid keybindings, styles and layouts are
+    /// recorded in `issues` and left out. The selected theme is resolved by the caller, once the
+    /// themes have been merged with the defaults.
+    fn from_source(
+        source: &config::Config,
+        issues: &mut Vec<ConfigIssue>,
+    ) -> Result<Self, ConfigError> {
+        let config: AppConfig = source.clone().try_deserialize()?;
+        let descriptions = match source.get("descriptions") {
+            Err(config::ConfigError::NotFound(_)) => HashMap::new(),
+            descriptions => descriptions?,
+        };
+
+        let mut keybindings = KeyBindings::default();
+        let mut key_conflicts = Vec::new();
+        for (mode, name, table) in mode_tables(source, "keybindings", issues) {
+            let keymap = keybindings.entry(mode).or_default();
+            for (key, value) in table_entries("keybindings", &name, table, issues) {
+                match parse_keybinding(&key, value.clone()) {
+                    Ok((keys, action)) => {
+                        // e.g. `<backtab>` and `<shift-backtab>`
+                        if let Some(previous) = keymap.insert(keys.clone(), action.clone()) {
+                            key_conflicts.push(KeyConflict {
+                                mode,
+                                keys,
+                                kind: ConflictKind::Duplicate {
+                                    actions: [previous, action],
+                                },
+                            });
+                        }
+                    }
+                    Err(reason) => issues.push(
+                        ConfigIssue::new("keybindings", reason)
+                            .at(&value)
+                            .mode(&name)
+                            .key(&key),
+                    ),
+                }
+            }
+        }
+
+        let mut themes: HashMap<String, ThemeConfig> = HashMap::new();
+        for (name, table) in section_entries(source, "themes", issues) {
+            let theme = themes.entry(name.clone()).or_default();
+            for (key, value) in table_entries("themes", &name, table, issues) {
+                let parsed = value.clone().into_string().map_err(|err| err.to_string());
+                let parsed = if key == "extends" {
+                    parsed.map(|extends| theme.extends = Some(extends))
+                } else {
+                    parsed
+                        .and_then(|style| try_parse_style(&style))
+                        .map(|style| {
+                            theme.styles.insert(key.clone(), style);
+                        })
+                };
+                if let Err(reason) = parsed {
+                    issues.push(
+                        ConfigIssue::new("themes", reason)
+                            .at(&value)
+                            .mode(&name)
+                            .key(&key),
+                    );
+                }
+            }
+        }
+
+        let mut layouts = HashMap::new();
+        for (mode, name, value) in mode_tables(source, "layouts", issues) {
+            match value.clone().try_deserialize() {
+                Ok(layout) => {
+                    layouts.insert(mode, layout);
+                }
+                Err(err) => issues.push(ConfigIssue::new("layouts", err).at(&value).mode(&name)),
+            }
+        }
+
+        Ok(Self {
+            config,
+            keybindings,
+            themes,
+            theme: Theme::default(),
+            layouts,
+            descriptions,
+            key_conflicts,
+        })
+    }
+}
+
+/// The values of a section that is keyed by mode, such as `keybindings`, with the name of the
+/// mode as it was written. Unknown modes are recorded in `issues` and left out.
+fn mode_tables(
+    source: &config::Config,
+    section: &str,
+    issues: &mut Vec<ConfigIssue>,
+) -> Vec<(Mode, String, config::Value)> {
+    section_entries(source, section, issues)
+        .into_iter()
+        .filter_map(|(name, value)| {
+            match config::Value::new(None, name.as_str()).try_deserialize::<Mode>() {
+                Ok(mode) => Some((mode, name, value)),
+                Err(_) => {
+                    let issue = ConfigIssue::new(section, "Unknown mode").at(&value);
+                    issues.push(issue.mode(&name));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The entries of a top-level section. If it is not a table, this is recorded in `issues` and no
+/// entries are returned.
+fn section_entries(
+    source: &config::Config,
+    section: &str,
+    issues: &mut Vec<ConfigIssue>,
+) -> Vec<(String, config::Value)> {
+    match source.get_table(section) {
+        Ok(table) => table.into_iter().collect(),
+        Err(config::ConfigError::NotFound(_)) => Vec::new(),
+        Err(err) => {
+            issues.push(ConfigIssue::new(section, err));
+            Vec::new()
+        }
+    }
+}
+
+/// The entries of the table of a mode or theme. If `value` is not a table, this is recorded in `issues`
+/// and no entries are returned.
+fn table_entries(
+    section: &str,
+    mode: &str,
+    value: config::Value,
+    issues: &mut Vec<ConfigIssue>,
+) -> Vec<(String, config::Value)> {
+    match value.clone().into_table() {
+        Ok(table) => table.into_iter().collect(),
+        Err(err) => {
+            issues.push(ConfigIssue::new(section, err).at(&value).mode(mode));
+            Vec::new()
+        }
+    }
+}
 
-        Ok(cfg)
+/// The file that a value was read from. Tables that were merged from several sources do not
+/// have an origin, so the origin of the first value in them is used instead.
+///
+/// The config crate records paths relative to the working directory, so they are made absolute.
+fn origin(value: &config::Value) -> Option<PathBuf> {
+    if let Some(origin) = value.origin() {
+        let path = PathBuf::from(origin);
+        return Some(std::fs::canonicalize(&path).unwrap_or(path));
     }
+    match &value.kind {
+        config::ValueKind::Table(table) => table.values().find_map(origin),
+        config::ValueKind::Array(array) => array.iter().find_map(origin),
+        _ => None,
+    }
+}
+
+/// Parses a keybinding entry, e.g. `"<ctrl-d>": "Quit"`.
+fn parse_keybinding(key: &str, value: config::Value) -> Result<(Vec<KeyEvent>, Action), String> {
+    let keys = parse_key_sequence(key)?;
+    let action = value.try_deserialize().map_err(|err| err.to_string())?;
+    Ok((keys, action))
 }
 
 pub fn get_data_dir() -> PathBuf {
@@ -107,6 +620,15 @@ pub fn get_data_dir() -> PathBuf {
     directory
 }
 
+/// The directory of the configuration that applies to every user, if the platform has one.
+pub fn get_system_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA").map(|dir| PathBuf::from(dir).join(env!("CARGO_PKG_NAME")))
+    } else {
+        Some(PathBuf::from("/etc").join(env!("CARGO_PKG_NAME")))
+    }
+}
+
 pub fn get_config_dir() -> PathBuf {
     let directory = if let Some(s) = CONFIG_FOLDER.clone() {
         s
@@ -118,6 +640,94 @@ pub fn get_config_dir() -> PathBuf {
     directory
 }
 
+/// Sends [`Action::ReloadConfig`] when a configuration file of `sources` is created, changed or
+/// removed. The files are watched until the returned debouncer is dropped.
+pub fn watch_config(
+    sources: &ConfigSources,
+    tx: UnboundedSender<Action>,
+) -> Result<Debouncer<RecommendedWatcher, RecommendedCache>> {
+    let file = sources.file.clone();
+    let handler = move |result: DebounceEventResult| match result {
+        Ok(events)
+            if events
+                .iter()
+                .any(|event| is_config_change(event, file.as_deref())) =>
+        {
+            debug!("Configuration changed: {events:?}");
+            let _ = tx.send(Action::ReloadConfig);
+        }
+        Ok(_) => {}
+        Err(errors) => error!("Failed to watch the configuration: {errors:?}"),
+    };
+    let mut debouncer = new_debouncer(RELOAD_DEBOUNCE, None, handler)?;
+    let dirs = match &sources.file {
+        // editors often replace the file instead of writing it, so its directory is watched
+        Some(file) => vec![match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }],
+        None => sources.config_dirs(),
+    };
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        debouncer.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok(debouncer)
+}
+
+/// The directories that configuration files are read from, from the lowest precedence to the
+/// highest: the system, the user and the project in the working directory.
+pub fn config_dirs() -> Vec<PathBuf> {
+    let dirs = get_system_config_dir()
+        .into_iter()
+        .chain([get_config_dir(), PathBuf::from(".").join(".config")]);
+    // the user config directory falls back to the project one, and `<NAME>_CONFIG` often points
+    // to it, so that its files would be read twice
+    unique_dirs(dirs)
+}
+
+/// Removes the directories that are the same as an earlier one, also when they are written
+/// differently, e.g. as a relative and an absolute path.
+fn unique_dirs(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut unique: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+    for dir in dirs {
+        let canonical = dir.canonicalize().ok();
+        let seen = unique.iter().any(|(other, other_canonical)| {
+            *other == dir || (canonical.is_some() && *other_canonical == canonical)
+        });
+        if !seen {
+            unique.push((dir, canonical));
+        }
+    }
+    unique.into_iter().map(|(dir, _)| dir).collect()
+}
+
+/// The configuration files that exist in `dir`, in the order they are read.
+pub fn config_files_in(dir: &Path) -> Vec<PathBuf> {
+    CONFIG_FILES
+        .iter()
+        .map(|(file, _)| dir.join(file))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Whether the event changed one of the configuration files, or the explicit `file` if there is
+/// one. Reading the files is reported as an access event, which is ignored so that a reload does
+/// not trigger another one.
+fn is_config_change(event: &DebouncedEvent, file: Option<&Path>) -> bool {
+    let is_watched = |path: &Path| match file {
+        Some(file) => path.file_name() == file.file_name(),
+        None => is_config_file(path),
+    };
+    !event.kind.is_access() && event.paths.iter().any(|path| is_watched(path))
+}
+
+/// Whether `path` is one of the files that [`Config::new`] reads.
+fn is_config_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| CONFIG_FILES.iter().any(|(file, _)| *file == name))
+}
+
 fn project_directory() -> Option<ProjectDirs> {
     ProjectDirs::from("com", "kdheepak", env!("CARGO_PKG_NAME"))
 }
@@ -137,20 +747,58 @@ impl<'de> Deserialize<'de> for KeyBindings {
             .map(|(mode, inner_map)| {
                 let converted_inner_map = inner_map
                     .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
+                    .map(|(key_str, cmd)| {
+                        parse_key_sequence(&key_str)
+                            .map(|keys| (keys, cmd))
+                            .map_err(serde::de::Error::custom)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok((mode, converted_inner_map))
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         Ok(KeyBindings(keybindings))
     }
 }
 
+impl Serialize for KeyBindings {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let keybindings: BTreeMap<String, BTreeMap<String, &Action>> = self
+            .iter()
+            .map(|(mode, keymap)| {
+                let keymap = keymap
+                    .iter()
+                    .map(|(keys, action)| (key_sequence_to_string(keys), action))
+                    .collect();
+                (format!("{mode:?}"), keymap)
+            })
+            .collect();
+        keybindings.serialize(serializer)
+    }
+}
+
+/// Parses a key such as `ctrl-a`. A `release-` or `repeat-` prefix binds the release of the key
+/// or its repeats while it is held, which terminals only report with the kitty keyboard protocol.
 fn parse_key_event(raw: &str) -> Result<KeyEvent, String> {
     let raw_lower = raw.to_ascii_lowercase();
-    let (remaining, modifiers) = extract_modifiers(&raw_lower);
-    parse_key_code_with_modifiers(remaining, modifiers)
+    let (remaining, kind) = extract_kind(&raw_lower);
+    let (remaining, modifiers) = extract_modifiers(remaining);
+    let mut key_event = parse_key_code_with_modifiers(remaining, modifiers)?;
+    key_event.kind = kind;
+    Ok(key_event)
+}
+
+fn extract_kind(raw: &str) -> (&str, KeyEventKind) {
+    if let Some(rest) = raw.strip_prefix("release-") {
+        (rest, KeyEventKind::Release)
+    } else if let Some(rest) = raw.strip_prefix("repeat-") {
+        (rest, KeyEventKind::Repeat)
+    } else {
+        (raw, KeyEventKind::Press)
+    }
 }
 
 fn extract_modifiers(raw: &str) -> (&str, KeyModifiers) {
@@ -246,7 +894,7 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
         KeyCode::Delete => "delete",
         KeyCode::Insert => "insert",
         KeyCode::F(c) => {
-            char = format!("f({c})");
+            char = format!("f{c}");
             &char
         }
         KeyCode::Char(' ') => "space",
@@ -281,9 +929,14 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
         modifiers.push("alt");
     }
 
-    let mut key = modifiers.join("-");
+    let mut key = match key_event.kind {
+        KeyEventKind::Press => String::new(),
+        KeyEventKind::Repeat => "repeat-".to_string(),
+        KeyEventKind::Release => "release-".to_string(),
+    };
+    key.push_str(&modifiers.join("-"));
 
-    if !key.is_empty() {
+    if !modifiers.is_empty() {
         key.push('-');
     }
     key.push_str(key_code);
@@ -291,6 +944,14 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     key
 }
 
+/// Formats a key sequence in the same syntax that [`parse_key_sequence`] accepts, e.g. `<g><g>`.
+pub fn key_sequence_to_string(key_events: &[KeyEvent]) -> String {
+    key_events
+        .iter()
+        .map(|key_event| format!("<{}>", key_event_to_string(key_event)))
+        .collect()
+}
+
 pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
         return Err(format!("Unable to parse `{}`", raw));
@@ -318,138 +979,246 @@ pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     sequences.into_iter().map(parse_key_event).collect()
 }
 
-#[derive(Clone, Debug, Default, Deref, DerefMut)]
-pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
+/// The styles of a theme, keyed by semantic names like `border.focused`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deref, DerefMut)]
+pub struct Styles(pub HashMap<String, Style>);
 
 impl<'de> Deserialize<'de> for Styles {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
+        let parsed_map = HashMap::<String, String>::deserialize(deserializer)?;
 
         let styles = parsed_map
             .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(str, style)| (str, parse_style(&style)))
-                    .collect();
-                (mode, converted_inner_map)
+            .map(|(key, style)| {
+                try_parse_style(&style)
+                    .map(|style| (key, style))
+                    .map_err(serde::de::Error::custom)
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         Ok(Styles(styles))
     }
 }
 
+/// The colors that have a name, in the order of their index in the 256-color palette. Their
+/// bright variants follow them at indices 8 to 15.
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Every modifier by the name that is used to write it.
+const MODIFIERS: [(&str, Modifier); 9] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underline", Modifier::UNDERLINED),
+    ("slow_blink", Modifier::SLOW_BLINK),
+    ("rapid_blink", Modifier::RAPID_BLINK),
+    ("inverse", Modifier::REVERSED),
+    ("hidden", Modifier::HIDDEN),
+    ("crossed_out", Modifier::CROSSED_OUT),
+];
+
+/// Other names that modifiers are read from.
+const MODIFIER_ALIASES: [(&str, Modifier); 4] = [
+    ("underlined", Modifier::UNDERLINED),
+    ("blink", Modifier::SLOW_BLINK),
+    ("reversed", Modifier::REVERSED),
+    ("strikethrough", Modifier::CROSSED_OUT),
+];
+
+/// Parses a style like [`parse_style`], but fails on words that are not recognized instead of
+/// ignoring them.
+pub fn try_parse_style(line: &str) -> Result<Style, String> {
+    let (style, unknown) = parse_style_parts(line);
+    match unknown.first() {
+        Some(color) => Err(format!("Unknown color `{color}` in style `{line}`")),
+        None => Ok(style),
+    }
+}
+
+/// Parses a style such as `bold italic bright red on #1e1e2e`.
+///
+/// The words before `on` describe the foreground and the ones after it the background. Each part
+/// is a list of modifiers (see [`MODIFIERS`]) and at most one color, which is one of
+///
+/// - a name like `red`, or `bright red` for its bright variant
+/// - `colorN` for an index in the 256-color palette, or `bright colorN` for N from 0 to 7
+/// - `rgbRGB` for the 6x6x6 color cube, with each component from 0 to 5
+/// - `grayN` for the 24 shades of gray, from 0 to 23
+/// - `#rrggbb` or `#rgb` for a true color
+/// - `reset` for the default color of the terminal
+///
+/// Colors that are not recognized are ignored. Use [`try_parse_style`] to reject them.
 pub fn parse_style(line: &str) -> Style {
-    let (foreground, background) =
-        line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
-    let foreground = process_color_string(foreground);
-    let background = process_color_string(&background.replace("on ", ""));
+    let (style, _) = parse_style_parts(line);
+    style
+}
 
-    let mut style = Style::default();
-    if let Some(fg) = parse_color(&foreground.0) {
-        style = style.fg(fg);
-    }
-    if let Some(bg) = parse_color(&background.0) {
-        style = style.bg(bg);
+/// Parses a style, returning the colors that were not recognized alongside it.
+fn parse_style_parts(line: &str) -> (Style, Vec<String>) {
+    let line = line.to_lowercase();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (foreground, background) = match words.iter().position(|word| *word == "on") {
+        Some(on) => (words[..on].join(" "), words[on + 1..].join(" ")),
+        None => (words.join(" "), String::new()),
+    };
+    let foreground = process_color_string(&foreground);
+    let background = process_color_string(&background);
+
+    let mut style = Style::default().add_modifier(foreground.1 | background.1);
+    let mut unknown = Vec::new();
+    for (color, is_foreground) in [(foreground.0, true), (background.0, false)] {
+        if color.is_empty() {
+            continue;
+        }
+        match parse_color(&color) {
+            Some(color) if is_foreground => style = style.fg(color),
+            Some(color) => style = style.bg(color),
+            None => unknown.push(color),
+        }
     }
-    style = style.add_modifier(foreground.1 | background.1);
-    style
+    (style, unknown)
 }
 
+/// Splits one part of a style into its color and its modifiers.
 fn process_color_string(color_str: &str) -> (String, Modifier) {
-    let color = color_str
-        .replace("grey", "gray")
-        .replace("bright ", "")
-        .replace("bold ", "")
-        .replace("underline ", "")
-        .replace("inverse ", "");
-
     let mut modifiers = Modifier::empty();
-    if color_str.contains("underline") {
-        modifiers |= Modifier::UNDERLINED;
-    }
-    if color_str.contains("bold") {
-        modifiers |= Modifier::BOLD;
-    }
-    if color_str.contains("inverse") {
-        modifiers |= Modifier::REVERSED;
+    let mut color = Vec::new();
+    for word in color_str.split_whitespace() {
+        match MODIFIERS
+            .iter()
+            .chain(MODIFIER_ALIASES.iter())
+            .find(|(name, _)| *name == word)
+        {
+            Some((_, modifier)) => modifiers |= *modifier,
+            None => color.push(word),
+        }
     }
-
-    (color, modifiers)
+    (color.join(" "), modifiers)
 }
 
 fn parse_color(s: &str) -> Option<Color> {
-    let s = s.trim_start();
-    let s = s.trim_end();
-    if s.contains("bright color") {
-        let s = s.trim_start_matches("bright ");
-        let c = s
-            .trim_start_matches("color")
-            .parse::<u8>()
-            .unwrap_or_default();
-        Some(Color::Indexed(c.wrapping_shl(8)))
-    } else if s.contains("color") {
-        let c = s
-            .trim_start_matches("color")
-            .parse::<u8>()
-            .unwrap_or_default();
-        Some(Color::Indexed(c))
-    } else if s.contains("gray") {
-        let c = 232
-            + s.trim_start_matches("gray")
-                .parse::<u8>()
-                .unwrap_or_default();
-        Some(Color::Indexed(c))
-    } else if s.contains("rgb") {
-        let red = (s.as_bytes()[3] as char).to_digit(10).unwrap_or_default() as u8;
-        let green = (s.as_bytes()[4] as char).to_digit(10).unwrap_or_default() as u8;
-        let blue = (s.as_bytes()[5] as char).to_digit(10).unwrap_or_default() as u8;
-        let c = 16 + red * 36 + green * 6 + blue;
-        Some(Color::Indexed(c))
-    } else if s == "bold black" {
-        Some(Color::Indexed(8))
-    } else if s == "bold red" {
-        Some(Color::Indexed(9))
-    } else if s == "bold green" {
-        Some(Color::Indexed(10))
-    } else if s == "bold yellow" {
-        Some(Color::Indexed(11))
-    } else if s == "bold blue" {
-        Some(Color::Indexed(12))
-    } else if s == "bold magenta" {
-        Some(Color::Indexed(13))
-    } else if s == "bold cyan" {
-        Some(Color::Indexed(14))
-    } else if s == "bold white" {
-        Some(Color::Indexed(15))
-    } else if s == "black" {
-        Some(Color::Indexed(0))
-    } else if s == "red" {
-        Some(Color::Indexed(1))
-    } else if s == "green" {
-        Some(Color::Indexed(2))
-    } else if s == "yellow" {
-        Some(Color::Indexed(3))
-    } else if s == "blue" {
-        Some(Color::Indexed(4))
-    } else if s == "magenta" {
-        Some(Color::Indexed(5))
-    } else if s == "cyan" {
-        Some(Color::Indexed(6))
-    } else if s == "white" {
-        Some(Color::Indexed(7))
+    let s = s.trim().to_lowercase().replace("grey", "gray");
+    if let Some(s) = s.strip_prefix("bright ") {
+        return match parse_color(s)? {
+            Color::Indexed(c) if c < 8 => Some(Color::Indexed(c + 8)),
+            _ => None,
+        };
+    }
+    if s == "reset" {
+        Some(Color::Reset)
+    } else if let Some(hex) = s.strip_prefix('#') {
+        parse_hex_color(hex)
+    } else if let Some(index) = COLOR_NAMES.iter().position(|name| *name == s) {
+        Some(Color::Indexed(index as u8))
+    } else if let Some(c) = s.strip_prefix("color") {
+        Some(Color::Indexed(c.parse::<u8>().ok()?))
+    } else if let Some(level) = s.strip_prefix("gray") {
+        // the 24 shades of gray start at index 232
+        let level = match level {
+            "" => 0,
+            level => level.parse::<u8>().ok().filter(|level| *level < 24)?,
+        };
+        Some(Color::Indexed(232 + level))
+    } else if let Some(rgb) = s.strip_prefix("rgb") {
+        // each component of the 6x6x6 color cube is a digit from 0 to 5
+        let digits = rgb
+            .chars()
+            .map(|c| c.to_digit(6).map(|digit| digit as u8))
+            .collect::<Option<Vec<_>>>()?;
+        let [red, green, blue] = digits[..] else {
+            return None;
+        };
+        Some(Color::Indexed(16 + red * 36 + green * 6 + blue))
     } else {
         None
     }
 }
 
+/// Parses the digits of a `#rrggbb` or `#rgb` color.
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let component = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some(Color::Rgb(
+            component(&hex[0..2])?,
+            component(&hex[2..4])?,
+            component(&hex[4..6])?,
+        )),
+        // each digit is repeated, so `#f80` is `#ff8800`
+        3 => Some(Color::Rgb(
+            component(&hex[0..1])? * 17,
+            component(&hex[1..2])? * 17,
+            component(&hex[2..3])? * 17,
+        )),
+        _ => None,
+    }
+}
+
+/// Writes a style in the syntax that [`parse_style`] reads, so that it can be written back to a
+/// configuration file. Modifiers that the style removes are not written.
+pub fn style_to_string(style: &Style) -> String {
+    let mut words: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|(_, modifier)| style.add_modifier.contains(*modifier))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if let Some(fg) = style.fg {
+        words.push(color_to_string(fg));
+    }
+    if let Some(bg) = style.bg {
+        words.push("on".to_string());
+        words.push(color_to_string(bg));
+    }
+    words.join(" ")
+}
+
+/// Writes a color in the syntax that [`parse_style`] reads. The named colors of ratatui are
+/// written as their equivalent in the 256-color palette.
+pub fn color_to_string(color: Color) -> String {
+    let index = match color {
+        Color::Reset => return "reset".to_string(),
+        Color::Rgb(red, green, blue) => return format!("#{red:02x}{green:02x}{blue:02x}"),
+        Color::Indexed(index) => index,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+    match index {
+        0..=7 => COLOR_NAMES[index as usize].to_string(),
+        8..=15 => format!("bright {}", COLOR_NAMES[index as usize - 8]),
+        16..=231 => {
+            let cube = index - 16;
+            format!("rgb{}{}{}", cube / 36, cube / 6 % 6, cube % 6)
+        }
+        _ => format!("gray{}", index - 232),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use color_eyre::eyre::eyre;
     use pretty_assertions::assert_eq;
+    use ratatui::style::Stylize;
 
     use super::*;
 
@@ -471,6 +1240,14 @@ mod tests {
         assert_eq!(style.bg, Some(Color::Indexed(4)));
     }
 
+    #[test]
+    fn test_parse_style_modifiers_only() {
+        assert_eq!(
+            try_parse_style("bold inverse"),
+            Ok(Style::new().bold().reversed())
+        );
+    }
+
     #[test]
     fn test_parse_style_modifiers() {
         let style = parse_style("underline red on blue");
@@ -500,9 +1277,81 @@ mod tests {
         assert_eq!(color, None);
     }
 
+    #[test]
+    fn test_parse_color_out_of_range() {
+        assert_eq!(parse_color("rgb12"), None);
+        assert_eq!(parse_color("rgb126"), None);
+        assert_eq!(parse_color("gray24"), None);
+        assert_eq!(parse_color("color256"), None);
+    }
+
+    #[test]
+    fn test_parse_style_all_modifiers() {
+        let style = parse_style(
+            "bold dim italic underlined blink rapid_blink reversed hidden strikethrough red",
+        );
+        assert_eq!(style.add_modifier, Modifier::all());
+        assert_eq!(style.fg, Some(Color::Indexed(1)));
+    }
+
+    #[test]
+    fn test_parse_color_bright() {
+        assert_eq!(parse_color("bright red"), Some(Color::Indexed(9)));
+        assert_eq!(parse_color("bright color3"), Some(Color::Indexed(11)));
+        assert_eq!(parse_color("bright color9"), None);
+        assert_eq!(
+            parse_style("bright black on bright white"),
+            Style::new().fg(Color::Indexed(8)).bg(Color::Indexed(15))
+        );
+    }
+
+    #[test]
+    fn test_parse_color_hex_and_reset() {
+        assert_eq!(parse_color("#1E1e2e"), Some(Color::Rgb(0x1e, 0x1e, 0x2e)));
+        assert_eq!(parse_color("#f80"), Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#gggggg"), None);
+        assert_eq!(parse_color("reset"), Some(Color::Reset));
+        assert_eq!(parse_color("grey3"), Some(Color::Indexed(235)));
+    }
+
+    #[test]
+    fn test_style_round_trip() {
+        for line in [
+            "",
+            "bold",
+            "red",
+            "on blue",
+            "bold italic bright red on #1e1e2e",
+            "underline inverse rgb504 on gray7",
+            "crossed_out rgb012 on reset",
+        ] {
+            let style = parse_style(line);
+            assert_eq!(style_to_string(&style), line);
+            assert_eq!(try_parse_style(&style_to_string(&style)), Ok(style));
+        }
+        assert_eq!(parse_color("color200"), parse_color("rgb504"));
+        assert_eq!(
+            style_to_string(&Style::new().light_red().on_dark_gray()),
+            "bright red on bright black"
+        );
+    }
+
+    #[test]
+    fn test_try_parse_style() {
+        assert_eq!(
+            try_parse_style("bold red on blue"),
+            Ok(parse_style("bold red on blue"))
+        );
+        assert_eq!(
+            try_parse_style("red on bleu"),
+            Err("Unknown color `bleu` in style `red on bleu`".to_string())
+        );
+    }
+
     #[test]
     fn test_config() -> Result<()> {
-        let c = Config::new()?;
+        let c = Config::from_sources(&ConfigSources::defaults_only())?;
         assert_eq!(
             c.keybindings
                 .get(&Mode::Home)
@@ -511,6 +1360,232 @@ mod tests {
                 .unwrap(),
             &Action::Quit
         );
+        assert_eq!(c.config.key_sequence_timeout, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_screen_actions_in_keybindings() {
+        let keybindings: KeyBindings = json5::from_str(
+            r#"{ "Home": { "<p>": { "PushScreen": "Home" }, "<esc>": "PopScreen" } }"#,
+        )
+        .unwrap();
+        let keymap = keybindings.get(&Mode::Home).unwrap();
+        assert_eq!(
+            keymap.get(&parse_key_sequence("<p>").unwrap()),
+            Some(&Action::PushScreen(Mode::Home))
+        );
+        assert_eq!(
+            keymap.get(&parse_key_sequence("<esc>").unwrap()),
+            Some(&Action::PopScreen)
+        );
+    }
+
+    #[test]
+    fn test_invalid_keybinding_is_an_error() {
+        let result = json5::from_str::<KeyBindings>(r#"{ "Home": { "<ctrl-q": "Quit" } }"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_collects_every_issue() -> Result<()> {
+        let path = env::temp_dir().join(format!("config-issues-{}.json5", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "keybindings": {
+                    "Home": { "<Ctrl-dd>": "Quit", "<q>": "Quit", "<x>": "Explode" },
+                    "Nowhere": {},
+                },
+                "themes": { "dark": { "title": "red on bleu" } },
+            }"#,
+        )?;
+        let source = config::Config::builder()
+            .add_source(config::File::from(path.as_path()))
+            .build()?;
+        std::fs::remove_file(&path)?;
+        let mut issues = Vec::new();
+        let config = Config::from_source(&source, &mut issues)?;
+        assert_eq!(config.keybindings[&Mode::Home].len(), 1);
+        // the config crate reports the file relative to the working directory
+        let file_name = path.file_name();
+        for issue in issues.iter_mut() {
+            if issue.mode.as_deref() != Some("nowhere") {
+                assert_eq!(issue.file.as_deref().and_then(Path::file_name), file_name);
+            }
+            issue.file = None;
+        }
+        issues.sort_by_key(ToString::to_string);
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "keybindings.home.<ctrl-dd>: Unable to parse dd",
+                "keybindings.home.<x>: enum Action does not have variant constructor Explode",
+                "keybindings.nowhere: Unknown mode",
+                "themes.dark.title: Unknown color `bleu` in style `red on bleu`",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_file() -> Result<()> {
+        let path = env::temp_dir().join(format!("config-check-{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            "keybindings:\n  Home:\n    <g><x>: Quit\n    <y>: Yank\nthemes:\n  dark:\n    border: bleu\n",
+        )?;
+        let issues = Config::from_sources_lenient(&ConfigSources {
+            file: Some(path.clone()),
+            ..Default::default()
+        });
+        std::fs::remove_file(&path)?;
+        let (_, issues) = issues?;
+        let mut issues: Vec<String> = issues
+            .into_iter()
+            .map(|issue| {
+                format!(
+                    "{}.{}",
+                    issue.mode.unwrap_or_default(),
+                    issue.key.unwrap_or_default()
+                )
+            })
+            .collect();
+        issues.sort();
+        assert_eq!(issues, vec!["dark.border", "home.<y>"]);
+        let missing = ConfigSources {
+            file: Some(PathBuf::from("missing.toml")),
+            ..Default::default()
+        };
+        assert!(Config::from_sources_lenient(&missing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_precedence() -> Result<()> {
+        let path = env::temp_dir().join(format!("config-layers-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "tick_rate = 10\nframe_rate = 20\nkey_sequence_timeout = 30\n",
+        )?;
+        let variable = format!("{}__FRAME_RATE", PROJECT_NAME.clone());
+        let config = Config::from_sources(&ConfigSources {
+            file: Some(path.clone()),
+            tick_rate: Some(50.0),
+            environment: Some(HashMap::from([(variable, "40".to_string())])),
+            ..Default::default()
+        });
+        std::fs::remove_file(&path)?;
+        let config = config?.config;
+        assert_eq!(config.key_sequence_timeout, 30);
+        assert_eq!(config.frame_rate, 40.0);
+        assert_eq!(config.tick_rate, 50.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_dirs() -> Result<()> {
+        let dir = env::temp_dir().join(format!("config-dirs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let other = dir.join("missing");
+        let dirs = unique_dirs([dir.clone(), other.clone(), dir.join("."), other.clone()]);
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(dirs, vec![dir, other]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rates_must_be_positive() -> Result<()> {
+        let (config, issues) = Config::from_sources_lenient(&ConfigSources {
+            tick_rate: Some(0.0),
+            ..ConfigSources::defaults_only()
+        })?;
+        assert_eq!(config.config.tick_rate, DEFAULT_TICK_RATE);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].to_string(), "tick_rate: Must be positive, not 0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_error_is_an_error() {
+        let error = ConfigError::from(
+            config::Config::builder()
+                .add_source(config::File::from_str("{ oops", config::FileFormat::Json5))
+                .build()
+                .unwrap_err(),
+        );
+        assert_eq!(error.issues.len(), 1);
+        assert_eq!(error.issues[0].file, None);
+    }
+
+    #[tokio::test]
+    async fn test_watch_config_sends_reload() -> Result<()> {
+        let dir = env::temp_dir().join(format!("config-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("config.json5");
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = watch_config(
+            &ConfigSources {
+                file: Some(file.clone()),
+                ..Default::default()
+            },
+            tx,
+        )?;
+        std::fs::write(&file, "{ tick_rate: 10 }")?;
+        let action = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+        drop(watcher);
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(action.ok().flatten(), Some(Action::ReloadConfig));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_config_file() {
+        assert!(is_config_file(Path::new(
+            "/home/user/.config/app/config.toml"
+        )));
+        assert!(!is_config_file(Path::new(
+            "/home/user/.config/app/config.toml~"
+        )));
+        assert!(!is_config_file(Path::new("/home/user/.config/app")));
+    }
+
+    #[test]
+    fn test_duplicate_keybindings() -> Result<()> {
+        let (config, issues) = Config::parse(
+            r#"{ "keybindings": { "Home": { "<backtab>": "Quit", "<shift-backtab>": "Quit" } } }"#,
+            config::FileFormat::Json5,
+        )?;
+        assert_eq!(issues, vec![]);
+        assert_eq!(config.key_conflicts.len(), 1);
+        assert_eq!(
+            config.key_conflicts[0].to_string(),
+            "Home: <shift-backtab>: bound twice, to `Quit` and `Quit`"
+        );
+        let (defaults, _) = Config::parse(CONFIG, config::FileFormat::Json5)?;
+        assert_eq!(defaults.key_conflicts, vec![]);
+        assert_eq!(
+            find_conflicts(&defaults.keybindings, &defaults.keybindings, false),
+            vec![]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_themes() -> Result<()> {
+        let c = Config::from_sources(&ConfigSources::defaults_only())?;
+        assert_eq!(c.theme.name, DEFAULT_THEME);
+        for name in ["dark", "light", "high-contrast"] {
+            let theme = Theme::resolve(name, &c.themes).map_err(|err| eyre!(err))?;
+            assert_ne!(theme.style("border.focused"), Style::default());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_layout() -> Result<()> {
+        let c = Config::from_sources(&ConfigSources::defaults_only())?;
+        assert!(c.layouts.contains_key(&Mode::Home));
         Ok(())
     }
 
@@ -577,6 +1652,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_kinds() {
+        assert_eq!(
+            parse_key_event("release-space").unwrap(),
+            KeyEvent::new_with_kind(
+                KeyCode::Char(' '),
+                KeyModifiers::empty(),
+                KeyEventKind::Release
+            )
+        );
+        let keys = parse_key_sequence("<Repeat-Ctrl-j><release-j>").unwrap();
+        assert_eq!(keys[0].kind, KeyEventKind::Repeat);
+        assert_eq!(keys[0].modifiers, KeyModifiers::CONTROL);
+        assert_eq!(key_sequence_to_string(&keys), "<repeat-ctrl-j><release-j>");
+        assert!(parse_key_event("ctrl-release-j").is_err());
+    }
+
+    #[test]
+    fn test_key_sequence_to_string() {
+        let keys = parse_key_sequence("<g><Ctrl-d><f1>").unwrap();
+        assert_eq!(key_sequence_to_string(&keys), "<g><ctrl-d><f1>");
+        assert_eq!(
+            parse_key_sequence(&key_sequence_to_string(&keys)).unwrap(),
+            keys
+        );
+    }
+
     #[test]
     fn test_invalid_keys() {
         assert!(parse_key_event("invalid-key").is_err());
@@ -0,0 +1,494 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{key_sequence_to_string, KeyBindings},
+};
+
+/// The largest count prefix that is accepted, so that a mistyped count cannot flood the
+/// application with actions.
+const MAX_COUNT: usize = 9999;
+
+/// The result of feeding a key to a [`KeySequence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyMatch {
+    /// The keys completed a binding. The action should be run `count` times.
+    Action { action: Action, count: usize },
+    /// The keys are a count prefix or the start of a longer binding.
+    Pending,
+    /// The keys are not bound.
+    Unbound,
+}
+
+/// State machine that matches multi-key bindings with vim-style count prefixes (e.g. `3j`).
+///
+/// A sequence is abandoned when no key is pressed for `timeout`. If the pending keys are a
+/// binding on their own as well as the start of a longer one (e.g. `<g>` and `<g><g>`), the
+/// shorter binding fires when the sequence times out.
+#[derive(Debug, Clone, Default)]
+pub struct KeySequence {
+    timeout: Duration,
+    pending: Vec<KeyEvent>,
+    count: Option<usize>,
+    fallback: Option<Action>,
+    last_key: Option<Instant>,
+}
+
+impl KeySequence {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a count or the start of a multi-key binding has been typed.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || !self.pending.is_empty()
+    }
+
+    /// Discards the pending keys and count.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+        self.fallback = None;
+        self.last_key = None;
+    }
+
+    /// Feeds a key press to the state machine.
+    pub fn push(
+        &mut self,
+        key: KeyEvent,
+        keymap: &HashMap<Vec<KeyEvent>, Action>,
+        now: Instant,
+    ) -> KeyMatch {
+        let mut sequence = self.pending.clone();
+        sequence.push(key);
+        let exact = keymap.get(&sequence);
+        let is_prefix = keymap
+            .keys()
+            .any(|keys| keys.len() > sequence.len() && keys.starts_with(&sequence));
+        match (exact, is_prefix) {
+            (Some(action), false) => {
+                let action = action.clone();
+                let count = self.count.unwrap_or(1);
+                self.reset();
+                KeyMatch::Action { action, count }
+            }
+            (exact, true) => {
+                self.pending = sequence;
+                self.fallback = exact.cloned();
+                self.last_key = Some(now);
+                KeyMatch::Pending
+            }
+            (None, false) => {
+                if let Some(digit) = self.count_digit(key) {
+                    let count = self.count.unwrap_or_default() * 10 + digit;
+                    self.count = Some(count.min(MAX_COUNT));
+                    self.last_key = Some(now);
+                    return KeyMatch::Pending;
+                }
+                let had_pending = !self.pending.is_empty();
+                self.reset();
+                if had_pending {
+                    // the sequence is broken, so the key may start a new one
+                    return self.push(key, keymap, now);
+                }
+                KeyMatch::Unbound
+            }
+        }
+    }
+
+    /// Abandons the sequence if it timed out, returning the action of the pending keys if they
+    /// are a binding on their own.
+    pub fn expire(&mut self, now: Instant) -> Option<(Action, usize)> {
+        let last_key = self.last_key?;
+        if now.duration_since(last_key) < self.timeout {
+            return None;
+        }
+        let count = self.count.unwrap_or(1);
+        let fallback = self.fallback.take().map(|action| (action, count));
+        self.reset();
+        fallback
+    }
+
+    /// The digit that the key adds to the count prefix, if it is part of one.
+    fn count_digit(&self, key: KeyEvent) -> Option<usize> {
+        if !self.pending.is_empty() || !key.modifiers.difference(KeyModifiers::SHIFT).is_empty() {
+            return None;
+        }
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        match c.to_digit(10)? {
+            // a leading zero is not a count
+            0 if self.count.is_none() => None,
+            digit => Some(digit as usize),
+        }
+    }
+}
+
+impl std::fmt::Display for KeySequence {
+    /// Shows the pending count and keys, e.g. `3<g>`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(count) = self.count {
+            write!(f, "{count}")?;
+        }
+        write!(f, "{}", key_sequence_to_string(&self.pending))
+    }
+}
+
+/// A keybinding that may not do what the user expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyConflict {
+    pub mode: Mode,
+    pub keys: Vec<KeyEvent>,
+    pub kind: ConflictKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// The keys are bound twice in the same file, and only one of the bindings is kept.
+    Duplicate { actions: [Action; 2] },
+    /// The keys replace a default binding.
+    OverridesDefault { default: Action, action: Action },
+    /// The keys are the start of a longer binding, so they only fire once the key sequence times
+    /// out.
+    Prefix { longer: Vec<KeyEvent> },
+    /// Terminals without the kitty keyboard protocol cannot send the keys.
+    Unsendable { reason: &'static str },
+}
+
+impl std::fmt::Display for KeyConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}: {}: ",
+            self.mode,
+            key_sequence_to_string(&self.keys)
+        )?;
+        match &self.kind {
+            ConflictKind::Duplicate {
+                actions: [first, second],
+            } => {
+                write!(f, "bound twice, to `{first}` and `{second}`")
+            }
+            ConflictKind::OverridesDefault { default, action } => {
+                write!(f, "`{action}` replaces the default `{default}`")
+            }
+            ConflictKind::Prefix { longer } => write!(
+                f,
+                "only fires after the key sequence timeout, as it starts {}",
+                key_sequence_to_string(longer)
+            ),
+            ConflictKind::Unsendable { reason } => {
+                write!(f, "cannot be sent by most terminals, {reason}")
+            }
+        }
+    }
+}
+
+/// Finds the keybindings that shadow each other, replace a default binding, or cannot be sent
+/// by the terminal. The conflicts are sorted by mode and keys.
+///
+/// With `keyboard_enhancement`, keys that only the kitty keyboard protocol can send are not
+/// reported, as the terminals that support it are expected to be used.
+pub fn find_conflicts(
+    keybindings: &KeyBindings,
+    defaults: &KeyBindings,
+    keyboard_enhancement: bool,
+) -> Vec<KeyConflict> {
+    let mut conflicts = Vec::new();
+    for (mode, keymap) in keybindings.iter() {
+        for (keys, action) in keymap {
+            let conflict = |kind| KeyConflict {
+                mode: *mode,
+                keys: keys.clone(),
+                kind,
+            };
+            let default = defaults.get(mode).and_then(|defaults| defaults.get(keys));
+            if let Some(default) = default.filter(|default| *default != action) {
+                conflicts.push(conflict(ConflictKind::OverridesDefault {
+                    default: default.clone(),
+                    action: action.clone(),
+                }));
+            }
+            let longer = keymap
+                .keys()
+                .filter(|other| other.len() > keys.len() && other.starts_with(keys))
+                .min_by_key(|other| key_sequence_to_string(other));
+            if let Some(longer) = longer {
+                conflicts.push(conflict(ConflictKind::Prefix {
+                    longer: longer.clone(),
+                }));
+            }
+            let unsendable = keys
+                .iter()
+                .find_map(|key| unsendable(key, keyboard_enhancement));
+            if let Some(reason) = unsendable {
+                conflicts.push(conflict(ConflictKind::Unsendable { reason }));
+            }
+        }
+    }
+    conflicts.sort_by_key(|conflict| {
+        (
+            format!("{:?}", conflict.mode),
+            key_sequence_to_string(&conflict.keys),
+        )
+    });
+    conflicts
+}
+
+/// Why the legacy terminal encoding cannot represent `key`, if it cannot and the kitty keyboard
+/// protocol is not enabled.
+fn unsendable(key: &KeyEvent, keyboard_enhancement: bool) -> Option<&'static str> {
+    if keyboard_enhancement {
+        return None;
+    }
+    if key.kind != KeyEventKind::Press {
+        return Some("as key releases and repeats need `keyboard_enhancement`");
+    }
+    if !key.modifiers.contains(KeyModifiers::CONTROL) {
+        return None;
+    }
+    let KeyCode::Char(c) = key.code else {
+        return None;
+    };
+    match c.to_ascii_lowercase() {
+        c if c.is_ascii_alphabetic() && key.modifiers.contains(KeyModifiers::SHIFT) => {
+            Some("as Ctrl-Shift-letter is sent as Ctrl-letter")
+        }
+        'i' => Some("as Ctrl-i is sent as Tab"),
+        'm' => Some("as Ctrl-m is sent as Enter"),
+        '[' => Some("as Ctrl-[ is sent as Esc"),
+        c if c.is_ascii_digit() => Some("as Ctrl-digit has no control code"),
+        _ => None,
+    }
+}
+
+/// Prepares a key event from the terminal to be matched against `keymap`.
+///
+/// The state of the lock keys is ignored. A repeat matches the binding of the key press unless
+/// the repeat is bound itself, so that holding a key works the same with and without the kitty
+/// keyboard protocol. Releases that are not bound are dropped, so that they do not break a
+/// pending key sequence.
+pub fn normalize_key(key: KeyEvent, keymap: &HashMap<Vec<KeyEvent>, Action>) -> Option<KeyEvent> {
+    let key = KeyEvent {
+        state: KeyEventState::NONE,
+        ..key
+    };
+    let is_bound = || keymap.keys().any(|keys| keys.contains(&key));
+    match key.kind {
+        KeyEventKind::Press => Some(key),
+        KeyEventKind::Repeat if !is_bound() => Some(KeyEvent {
+            kind: KeyEventKind::Press,
+            ..key
+        }),
+        KeyEventKind::Repeat => Some(key),
+        KeyEventKind::Release => is_bound().then_some(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::parse_key_sequence;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn keymap() -> HashMap<Vec<KeyEvent>, Action> {
+        HashMap::from([
+            (parse_key_sequence("<q>").unwrap(), Action::Quit),
+            (parse_key_sequence("<j>").unwrap(), Action::FocusNext),
+            (parse_key_sequence("<g>").unwrap(), Action::Help),
+            (parse_key_sequence("<g><g>").unwrap(), Action::ClearScreen),
+            (parse_key_sequence("<z><z>").unwrap(), Action::Suspend),
+        ])
+    }
+
+    fn key(raw: &str) -> KeyEvent {
+        parse_key_sequence(raw).unwrap()[0]
+    }
+
+    fn action(action: Action, count: usize) -> KeyMatch {
+        KeyMatch::Action { action, count }
+    }
+
+    fn keybindings(bindings: &[(&str, Action)]) -> KeyBindings {
+        let keymap = bindings
+            .iter()
+            .map(|(keys, action)| (parse_key_sequence(keys).unwrap(), action.clone()))
+            .collect();
+        KeyBindings(HashMap::from([(Mode::Home, keymap)]))
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let defaults = keybindings(&[("<q>", Action::Quit), ("<tab>", Action::FocusNext)]);
+        let user = keybindings(&[
+            ("<q>", Action::Help),
+            ("<tab>", Action::FocusNext),
+            ("<g>", Action::Help),
+            ("<g><g>", Action::ClearScreen),
+            ("<g><t>", Action::FocusNext),
+            ("<ctrl-shift-a>", Action::Quit),
+            ("<ctrl-i>", Action::Quit),
+            ("<ctrl-a>", Action::Quit),
+            ("<release-space>", Action::Help),
+        ]);
+        let conflicts: Vec<String> = find_conflicts(&user, &defaults, false)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                "Home: <ctrl-i>: cannot be sent by most terminals, as Ctrl-i is sent as Tab",
+                "Home: <ctrl-shift-A>: cannot be sent by most terminals, as Ctrl-Shift-letter is \
+                 sent as Ctrl-letter",
+                "Home: <g>: only fires after the key sequence timeout, as it starts <g><g>",
+                "Home: <q>: `Help` replaces the default `Quit`",
+                "Home: <release-space>: cannot be sent by most terminals, as key releases and \
+                 repeats need `keyboard_enhancement`",
+            ]
+        );
+        assert_eq!(
+            find_conflicts(&user, &defaults, true)
+                .iter()
+                .filter(|conflict| matches!(conflict.kind, ConflictKind::Unsendable { .. }))
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_normalize_key() {
+        let mut keymap = keymap();
+        keymap.insert(vec![key("<release-space>")], Action::Help);
+        let held = KeyEvent {
+            state: KeyEventState::NUM_LOCK,
+            ..key("<repeat-j>")
+        };
+        assert_eq!(normalize_key(held, &keymap), Some(key("<j>")));
+        assert_eq!(normalize_key(key("<release-j>"), &keymap), None);
+        assert_eq!(
+            normalize_key(key("<release-space>"), &keymap),
+            Some(key("<release-space>"))
+        );
+        keymap.insert(vec![key("<repeat-j>")], Action::FocusPrevious);
+        assert_eq!(normalize_key(held, &keymap), Some(key("<repeat-j>")));
+    }
+
+    #[test]
+    fn test_single_key() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        assert_eq!(
+            keys.push(key("<q>"), &keymap(), now),
+            action(Action::Quit, 1)
+        );
+        assert_eq!(keys.push(key("<x>"), &keymap(), now), KeyMatch::Unbound);
+        assert!(!keys.is_pending());
+    }
+
+    #[test]
+    fn test_multi_key() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        assert_eq!(keys.push(key("<z>"), &keymap(), now), KeyMatch::Pending);
+        assert_eq!(keys.to_string(), "<z>");
+        assert_eq!(
+            keys.push(key("<z>"), &keymap(), now + TIMEOUT / 2),
+            action(Action::Suspend, 1)
+        );
+        assert!(!keys.is_pending());
+    }
+
+    #[test]
+    fn test_timeout_abandons_sequence() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        keys.push(key("<z>"), &keymap(), now);
+        assert_eq!(keys.expire(now + TIMEOUT / 2), None);
+        assert!(keys.is_pending());
+        assert_eq!(keys.expire(now + TIMEOUT), None);
+        assert!(!keys.is_pending());
+    }
+
+    #[test]
+    fn test_shadowed_binding_fires_on_timeout() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        assert_eq!(keys.push(key("<g>"), &keymap(), now), KeyMatch::Pending);
+        assert_eq!(keys.expire(now + TIMEOUT), Some((Action::Help, 1)));
+
+        keys.push(key("<g>"), &keymap(), now);
+        assert_eq!(
+            keys.push(key("<g>"), &keymap(), now),
+            action(Action::ClearScreen, 1)
+        );
+    }
+
+    #[test]
+    fn test_broken_sequence_restarts() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        keys.push(key("<z>"), &keymap(), now);
+        assert_eq!(
+            keys.push(key("<q>"), &keymap(), now),
+            action(Action::Quit, 1)
+        );
+    }
+
+    #[test]
+    fn test_count_prefix() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        assert_eq!(keys.push(key("<0>"), &keymap(), now), KeyMatch::Unbound);
+        assert_eq!(keys.push(key("<1>"), &keymap(), now), KeyMatch::Pending);
+        assert_eq!(keys.push(key("<0>"), &keymap(), now), KeyMatch::Pending);
+        assert_eq!(keys.push(key("<z>"), &keymap(), now), KeyMatch::Pending);
+        assert_eq!(keys.to_string(), "10<z>");
+        assert_eq!(
+            keys.push(key("<z>"), &keymap(), now),
+            action(Action::Suspend, 10)
+        );
+        keys.push(key("<3>"), &keymap(), now);
+        assert_eq!(
+            keys.push(key("<j>"), &keymap(), now),
+            action(Action::FocusNext, 3)
+        );
+    }
+
+    #[test]
+    fn test_count_is_capped() {
+        let mut keys = KeySequence::new(TIMEOUT);
+        let now = Instant::now();
+        for _ in 0..10 {
+            keys.push(key("<9>"), &keymap(), now);
+        }
+        assert_eq!(
+            keys.push(key("<j>"), &keymap(), now),
+            action(Action::FocusNext, MAX_COUNT)
+        );
+    }
+
+    #[test]
+    fn test_bound_digits_are_not_counts() {
+        let mut keymap = keymap();
+        keymap.insert(parse_key_sequence("<1>").unwrap(), Action::Help);
+        let mut keys = KeySequence::new(TIMEOUT);
+        assert_eq!(
+            keys.push(key("<1>"), &keymap, Instant::now()),
+            action(Action::Help, 1)
+        );
+    }
+}
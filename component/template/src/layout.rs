@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use color_eyre::{eyre::eyre, Result};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use serde::{de::Deserializer, ser::SerializeMap, Deserialize, Serialize, Serializer};
+use tracing::warn;
+
+use crate::components::Component;
+
+/// Declarative description of how the screen is split between components.
+///
+/// This is what is read from the `layouts` section of the configuration. Leaves refer to
+/// components by the name they were registered with in [`crate::app::App`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutConfig {
+    Component(String),
+    Split {
+        direction: Direction,
+        constraints: Vec<Constraint>,
+        children: Vec<LayoutConfig>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLayoutConfig {
+    Component(String),
+    Split {
+        #[serde(default)]
+        direction: Option<String>,
+        constraints: Vec<String>,
+        children: Vec<RawLayoutConfig>,
+    },
+}
+
+impl<'de> Deserialize<'de> for LayoutConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawLayoutConfig::deserialize(deserializer)?;
+        LayoutConfig::try_from(raw).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for LayoutConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            LayoutConfig::Component(name) => serializer.serialize_str(name),
+            LayoutConfig::Split {
+                direction,
+                constraints,
+                children,
+            } => {
+                let direction = match direction {
+                    Direction::Horizontal => "horizontal",
+                    Direction::Vertical => "vertical",
+                };
+                let constraints: Vec<String> = constraints
+                    .iter()
+                    .copied()
+                    .map(constraint_to_string)
+                    .collect();
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("direction", direction)?;
+                map.serialize_entry("constraints", &constraints)?;
+                map.serialize_entry("children", children)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl TryFrom<RawLayoutConfig> for LayoutConfig {
+    type Error = String;
+
+    fn try_from(raw: RawLayoutConfig) -> Result<Self, Self::Error> {
+        match raw {
+            RawLayoutConfig::Component(name) => Ok(LayoutConfig::Component(name)),
+            RawLayoutConfig::Split {
+                direction,
+                constraints,
+                children,
+            } => {
+                if constraints.len() != children.len() {
+                    return Err(format!(
+                        "Layout has {} constraints but {} children",
+                        constraints.len(),
+                        children.len()
+                    ));
+                }
+                Ok(LayoutConfig::Split {
+                    direction: parse_direction(direction.as_deref().unwrap_or("vertical"))?,
+                    constraints: constraints
+                        .iter()
+                        .map(|c| parse_constraint(c))
+                        .collect::<Result<_, _>>()?,
+                    children: children
+                        .into_iter()
+                        .map(LayoutConfig::try_from)
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// The names of the components in the layout, in depth-first order.
+    pub fn component_names(&self) -> Vec<&str> {
+        match self {
+            LayoutConfig::Component(name) => vec![name],
+            LayoutConfig::Split { children, .. } => children
+                .iter()
+                .flat_map(LayoutConfig::component_names)
+                .collect(),
+        }
+    }
+}
+
+pub fn parse_direction(raw: &str) -> Result<Direction, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "horizontal" => Ok(Direction::Horizontal),
+        "vertical" => Ok(Direction::Vertical),
+        _ => Err(format!("Unable to parse direction `{raw}`")),
+    }
+}
+
+/// Parses a constraint such as `length:3`, `min:0`, `percentage:50`, `fill:1` or `ratio:1/3`.
+pub fn parse_constraint(raw: &str) -> Result<Constraint, String> {
+    let error = || format!("Unable to parse constraint `{raw}`");
+    let (kind, value) = raw.split_once(':').ok_or_else(error)?;
+    let value = value.trim();
+    let number = || value.parse::<u16>().map_err(|_| error());
+    let constraint = match kind.trim().to_ascii_lowercase().as_str() {
+        "length" => Constraint::Length(number()?),
+        "min" => Constraint::Min(number()?),
+        "max" => Constraint::Max(number()?),
+        "percentage" => Constraint::Percentage(number()?),
+        "fill" => Constraint::Fill(number()?),
+        "ratio" => {
+            let (numerator, denominator) = value.split_once('/').ok_or_else(error)?;
+            let denominator = denominator.trim().parse().map_err(|_| error())?;
+            if denominator == 0 {
+                return Err(format!(
+                    "The ratio of constraint `{raw}` has a zero denominator"
+                ));
+            }
+            Constraint::Ratio(numerator.trim().parse().map_err(|_| error())?, denominator)
+        }
+        _ => return Err(error()),
+    };
+    Ok(constraint)
+}
+
+/// Formats a constraint in the same syntax that [`parse_constraint`] accepts.
+pub fn constraint_to_string(constraint: Constraint) -> String {
+    match constraint {
+        Constraint::Length(value) => format!("length:{value}"),
+        Constraint::Min(value) => format!("min:{value}"),
+        Constraint::Max(value) => format!("max:{value}"),
+        Constraint::Percentage(value) => format!("percentage:{value}"),
+        Constraint::Fill(value) => format!("fill:{value}"),
+        Constraint::Ratio(numerator, denominator) => format!("ratio:{numerator}/{denominator}"),
+    }
+}
+
+/// A tree of layout splits whose leaves hold the components of the application.
+///
+/// The area of every component is computed when the tree is resized, so components are drawn
+/// into their own part of the screen instead of all at `frame.area()`.
+pub enum LayoutNode {
+    Component {
+        /// The name the component has in the layout configuration.
+        name: String,
+        area: Rect,
+        component: Box<dyn Component>,
+    },
+    Split {
+        direction: Direction,
+        constraints: Vec<Constraint>,
+        children: Vec<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    /// Builds the tree described by `config`, taking the named components out of `components`.
+    ///
+    /// Components that are not referenced by the layout are not drawn and a warning is logged.
+    pub fn build(
+        config: &LayoutConfig,
+        mut components: HashMap<String, Box<dyn Component>>,
+    ) -> Result<Self> {
+        let node = Self::build_node(config, &mut components)?;
+        for name in components.keys() {
+            warn!("Component `{name}` is not part of the layout and will not be drawn");
+        }
+        Ok(node)
+    }
+
+    fn build_node(
+        config: &LayoutConfig,
+        components: &mut HashMap<String, Box<dyn Component>>,
+    ) -> Result<Self> {
+        match config {
+            LayoutConfig::Component(name) => {
+                let component = components
+                    .remove(name)
+                    .ok_or_else(|| eyre!("Unknown or duplicate component `{name}` in layout"))?;
+                Ok(LayoutNode::Component {
+                    name: name.clone(),
+                    area: Rect::default(),
+                    component,
+                })
+            }
+            LayoutConfig::Split {
+                direction,
+                constraints,
+                children,
+            } => Ok(LayoutNode::Split {
+                direction: *direction,
+                constraints: constraints.clone(),
+                children: children
+                    .iter()
+                    .map(|child| Self::build_node(child, components))
+                    .collect::<Result<_>>()?,
+            }),
+        }
+    }
+
+    /// Recomputes the area of every component in the tree.
+    pub fn resize(&mut self, area: Rect) {
+        match self {
+            LayoutNode::Component { area: current, .. } => *current = area,
+            LayoutNode::Split {
+                direction,
+                constraints,
+                children,
+            } => {
+                let areas = Layout::new(*direction, constraints.iter().copied()).split(area);
+                for (child, area) in children.iter_mut().zip(areas.iter()) {
+                    child.resize(*area);
+                }
+            }
+        }
+    }
+
+    /// The components of the tree with their areas, in depth-first order.
+    pub fn components_mut(&mut self) -> Vec<(Rect, &mut Box<dyn Component>)> {
+        self.named_components_mut()
+            .into_iter()
+            .map(|(_, area, component)| (area, component))
+            .collect()
+    }
+
+    /// The components of the tree with their names and areas, in depth-first order.
+    pub fn named_components_mut(&mut self) -> Vec<(&str, Rect, &mut Box<dyn Component>)> {
+        let mut components = Vec::new();
+        self.collect_components(&mut components);
+        components
+    }
+
+    fn collect_components<'a>(
+        &'a mut self,
+        components: &mut Vec<(&'a str, Rect, &'a mut Box<dyn Component>)>,
+    ) {
+        match self {
+            LayoutNode::Component {
+                name,
+                area,
+                component,
+            } => components.push((name, *area, component)),
+            LayoutNode::Split { children, .. } => {
+                for child in children {
+                    child.collect_components(components);
+                }
+            }
+        }
+    }
+
+    /// Takes the components out of the tree, by the name they have in the layout.
+    pub fn into_components(self) -> HashMap<String, Box<dyn Component>> {
+        let mut components = HashMap::new();
+        self.take_components(&mut components);
+        components
+    }
+
+    fn take_components(self, components: &mut HashMap<String, Box<dyn Component>>) {
+        match self {
+            LayoutNode::Component {
+                name, component, ..
+            } => {
+                components.insert(name, component);
+            }
+            LayoutNode::Split { children, .. } => {
+                for child in children {
+                    child.take_components(components);
+                }
+            }
+        }
+    }
+
+    /// The component at `index` in depth-first order.
+    pub fn component_mut(&mut self, index: usize) -> Option<&mut Box<dyn Component>> {
+        self.components_mut()
+            .into_iter()
+            .nth(index)
+            .map(|(_, component)| component)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use ratatui::Frame;
+
+    use super::*;
+
+    struct Empty;
+
+    impl Component for Empty {
+        fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn components(names: &[&str]) -> HashMap<String, Box<dyn Component>> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), Box::new(Empty) as Box<dyn Component>))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_constraint() {
+        assert_eq!(parse_constraint("length:3"), Ok(Constraint::Length(3)));
+        assert_eq!(parse_constraint("Min: 0"), Ok(Constraint::Min(0)));
+        assert_eq!(
+            parse_constraint("percentage:50"),
+            Ok(Constraint::Percentage(50))
+        );
+        assert_eq!(parse_constraint("fill:1"), Ok(Constraint::Fill(1)));
+        assert_eq!(parse_constraint("ratio:1/3"), Ok(Constraint::Ratio(1, 3)));
+        assert!(parse_constraint("length").is_err());
+        assert!(parse_constraint("width:3").is_err());
+        assert!(parse_constraint("ratio:1").is_err());
+        assert_eq!(
+            parse_constraint("ratio:1/0"),
+            Err("The ratio of constraint `ratio:1/0` has a zero denominator".to_string())
+        );
+    }
+
+    #[test]
+    fn test_deserialize_layout() {
+        let config: LayoutConfig = json5::from_str(
+            r#"{
+                "direction": "horizontal",
+                "constraints": ["length:10", "fill:1"],
+                "children": ["sidebar", { "constraints": ["fill:1"], "children": ["main"] }],
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            LayoutConfig::Split {
+                direction: Direction::Horizontal,
+                constraints: vec![Constraint::Length(10), Constraint::Fill(1)],
+                children: vec![
+                    LayoutConfig::Component("sidebar".to_string()),
+                    LayoutConfig::Split {
+                        direction: Direction::Vertical,
+                        constraints: vec![Constraint::Fill(1)],
+                        children: vec![LayoutConfig::Component("main".to_string())],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_layout_mismatched_constraints() {
+        let config = json5::from_str::<LayoutConfig>(
+            r#"{ "constraints": ["length:1"], "children": ["a", "b"] }"#,
+        );
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_resize() -> Result<()> {
+        let config = LayoutConfig::Split {
+            direction: Direction::Vertical,
+            constraints: vec![Constraint::Length(1), Constraint::Fill(1)],
+            children: vec![
+                LayoutConfig::Component("top".to_string()),
+                LayoutConfig::Component("bottom".to_string()),
+            ],
+        };
+        let mut layout = LayoutNode::build(&config, components(&["top", "bottom"]))?;
+        layout.resize(Rect::new(0, 0, 10, 5));
+        let areas: Vec<Rect> = layout
+            .components_mut()
+            .into_iter()
+            .map(|(a, _)| a)
+            .collect();
+        assert_eq!(areas, vec![Rect::new(0, 0, 10, 1), Rect::new(0, 1, 10, 4)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_unknown_component() {
+        let config = LayoutConfig::Component("missing".to_string());
+        assert!(LayoutNode::build(&config, components(&["other"])).is_err());
+    }
+}
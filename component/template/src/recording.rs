@@ -0,0 +1,167 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc::UnboundedSender,
+    time::{sleep_until, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::tui::Event;
+
+/// An event and when it was received, relative to the start of the recording.
+///
+/// Recordings are written as JSON lines, one event per line, e.g.
+/// `{"elapsed_ms":250,"event":{"Key":{"code":{"Char":"j"},...}}}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub elapsed_ms: u64,
+    pub event: Event,
+}
+
+/// Writes every event the application receives to a file, for `--record`.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|err| eyre!("Failed to create the recording {}: {err}", path.display()))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            start: Instant::now(),
+        })
+    }
+
+    /// Every line is flushed right away, so that the recording is complete up to the last event
+    /// when the application crashes, which is when it is most useful.
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        let recorded = RecordedEvent {
+            elapsed_ms: u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX),
+            event: event.clone(),
+        };
+        serde_json::to_writer(&mut self.writer, &recorded)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a recording written by [`Recorder`]. Empty lines are skipped.
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedEvent>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| eyre!("Failed to read the recording {}: {err}", path.display()))?;
+    parse_recording(&text).map_err(|err| eyre!("{}:{err}", path.display()))
+}
+
+fn parse_recording(text: &str) -> Result<Vec<RecordedEvent>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| eyre!("{}: {err}", index + 1))
+        })
+        .collect()
+}
+
+/// Sends the recorded events at the same pace as they were recorded.
+///
+/// Returns `false` if the replay was cancelled or nothing receives the events anymore.
+pub async fn replay(
+    events: Vec<RecordedEvent>,
+    event_tx: &UnboundedSender<Event>,
+    cancellation_token: &CancellationToken,
+) -> bool {
+    let start = Instant::now();
+    for recorded in events {
+        tokio::select! {
+            biased;
+            _ = cancellation_token.cancelled() => return false,
+            _ = sleep_until(start + Duration::from_millis(recorded.elapsed_ms)) => {}
+        }
+        if event_tx.send(recorded.event).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use pretty_assertions::assert_eq;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn events(recorded: &[RecordedEvent]) -> Vec<Event> {
+        recorded
+            .iter()
+            .map(|recorded| recorded.event.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_record_and_read() -> Result<()> {
+        let path = env::temp_dir().join(format!("recording-{}.jsonl", std::process::id()));
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL));
+        let mut recorder = Recorder::create(&path)?;
+        recorder.record(&Event::Init)?;
+        recorder.record(&key)?;
+        recorder.record(&Event::Resize(80, 24))?;
+        drop(recorder);
+        let recorded = read_recording(&path)?;
+        assert_eq!(
+            events(&recorded),
+            vec![Event::Init, key, Event::Resize(80, 24)]
+        );
+        assert!(recorded[0].elapsed_ms <= recorded[2].elapsed_ms);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let text =
+            "{\"elapsed_ms\":0,\"event\":\"Init\"}\n\n{\"elapsed_ms\":1,\"event\":\"Jump\"}\n";
+        let err = parse_recording(text).unwrap_err().to_string();
+        assert!(err.starts_with("3: unknown variant `Jump`"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let recorded = vec![
+            RecordedEvent {
+                elapsed_ms: 0,
+                event: Event::Tick,
+            },
+            RecordedEvent {
+                elapsed_ms: 20,
+                event: Event::Paste("hello".to_string()),
+            },
+        ];
+        let start = Instant::now();
+        assert!(replay(recorded.clone(), &event_tx, &CancellationToken::new()).await);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        let mut replayed = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            replayed.push(event);
+        }
+        assert_eq!(replayed, events(&recorded));
+
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        assert!(!replay(recorded, &event_tx, &cancelled).await);
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use ratatui::layout::{Direction, Rect};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use crate::{
+    action::Action,
+    app::Mode,
+    components::{fps::FpsCounter, home::Home, Component, ComponentTree},
+    config::Config,
+    layout::LayoutNode,
+    state::ScreenState,
+};
+
+/// The components that are active in a [`Mode`], laid out and with their own focus.
+///
+/// The application keeps a stack of screens. Only the screen on top of the stack receives
+/// events and is drawn; the screens below it keep their state until they are shown again.
+pub struct Screen {
+    pub mode: Mode,
+    pub layout: LayoutNode,
+    focused: Option<usize>,
+}
+
+impl Screen {
+    /// Builds the screen for `mode` using the layout configured for that mode.
+    pub fn new(mode: Mode, config: &Config) -> Result<Self> {
+        let layout = config
+            .layouts
+            .get(&mode)
+            .ok_or_else(|| eyre!("No layout configured for mode {mode:?}"))?;
+        Ok(Self {
+            mode,
+            layout: LayoutNode::build(layout, Self::components(mode))?,
+            focused: None,
+        })
+    }
+
+    /// The components that make up the screen of each mode, by the name used in the layout.
+    fn components(mode: Mode) -> HashMap<String, Box<dyn Component>> {
+        match mode {
+            Mode::Home => HashMap::from([
+                (
+                    "home".to_string(),
+                    Box::new(Home::new()) as Box<dyn Component>,
+                ),
+                ("fps".to_string(), Box::new(FpsCounter::default())),
+            ]),
+        }
+    }
+
+    /// Registers the handlers of every component, initializes them and focuses the first one,
+    /// or the one that was focused when the state was saved.
+    pub fn init(&mut self, tx: UnboundedSender<Action>, config: &Config, area: Rect) -> Result<()> {
+        self.layout.resize(area);
+        for (_, component) in self.layout.components_mut() {
+            component.register_action_handler_tree(tx.clone())?;
+        }
+        self.register_config(config)?;
+        for (area, component) in self.layout.components_mut() {
+            component.init_tree(area)?;
+        }
+        match self.focused.take() {
+            Some(index) if self.focusable_components().contains(&index) => {
+                self.set_focus(Some(index))
+            }
+            _ => self.focus_next(),
+        }
+    }
+
+    /// The state of the components that have something to keep across restarts.
+    pub fn save_state(&mut self) -> ScreenState {
+        let focused = self.focused_name();
+        let components = self
+            .layout
+            .named_components_mut()
+            .into_iter()
+            .filter_map(|(name, _, component)| Some((name.to_string(), component.save_state()?)))
+            .collect();
+        ScreenState {
+            mode: self.mode,
+            focused,
+            components,
+        }
+    }
+
+    /// Hands the saved state back to the components, before the screen is initialized.
+    ///
+    /// A component that fails to restore its state is left as it was, so that an outdated state
+    /// file does not prevent the application from starting. Components are matched by their name
+    /// in the layout, so the state still applies after the layout was rearranged.
+    pub fn restore_state(&mut self, state: &ScreenState) {
+        for (name, _, component) in self.layout.named_components_mut() {
+            let Some(saved) = state.components.get(name) else {
+                continue;
+            };
+            if let Err(err) = component.restore_state(saved.clone()) {
+                warn!("Failed to restore the state of `{name}`: {err}");
+            }
+        }
+        self.focused = state
+            .focused
+            .as_deref()
+            .and_then(|name| self.position_of(name));
+    }
+
+    /// Arranges the components as the layout in `config` describes, e.g. after the configuration
+    /// was reloaded. The components keep their state and the focused component keeps the focus.
+    ///
+    /// A layout that leaves out a component of the screen, or refers to one it does not have, is
+    /// rejected and the current layout is kept.
+    pub fn relayout(&mut self, config: &Config, area: Rect) -> Result<()> {
+        let mode = self.mode;
+        let layout = config
+            .layouts
+            .get(&mode)
+            .ok_or_else(|| eyre!("No layout configured for mode {mode:?}"))?;
+        let mut missing: HashSet<String> = self
+            .layout
+            .named_components_mut()
+            .into_iter()
+            .map(|(name, _, _)| name.to_string())
+            .collect();
+        for name in layout.component_names() {
+            if !missing.remove(name) {
+                return Err(eyre!("Unknown or duplicate component `{name}` in layout"));
+            }
+        }
+        if let Some(name) = missing.iter().next() {
+            return Err(eyre!("Component `{name}` is missing from the layout"));
+        }
+        let focused = self.focused_name();
+        let empty = LayoutNode::Split {
+            direction: Direction::Vertical,
+            constraints: Vec::new(),
+            children: Vec::new(),
+        };
+        let components = mem::replace(&mut self.layout, empty).into_components();
+        self.layout = LayoutNode::build(layout, components)?;
+        self.layout.resize(area);
+        self.focused = focused.and_then(|name| self.position_of(&name));
+        Ok(())
+    }
+
+    /// Passes `config` to every component, e.g. after the configuration files were reloaded.
+    pub fn register_config(&mut self, config: &Config) -> Result<()> {
+        for (_, component) in self.layout.components_mut() {
+            component.register_config_handler_tree(config.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Called when another screen is pushed on top of this one.
+    pub fn hide(&mut self) -> Result<()> {
+        match self.focused_component_mut() {
+            Some(component) => component.blur(),
+            None => Ok(()),
+        }
+    }
+
+    /// Called when this screen is back on top of the stack.
+    pub fn show(&mut self, area: Rect) -> Result<()> {
+        self.layout.resize(area);
+        match self.focused_component_mut() {
+            Some(component) => component.focus(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn focused_component_mut(&mut self) -> Option<&mut Box<dyn Component>> {
+        self.focused.and_then(|i| self.layout.component_mut(i))
+    }
+
+    pub fn focus_next(&mut self) -> Result<()> {
+        let focusable = self.focusable_components();
+        let next = match self.focused_position(&focusable) {
+            Some(position) => focusable.get((position + 1) % focusable.len()),
+            None => focusable.first(),
+        };
+        self.set_focus(next.copied())
+    }
+
+    pub fn focus_previous(&mut self) -> Result<()> {
+        let focusable = self.focusable_components();
+        let previous = match self.focused_position(&focusable) {
+            Some(position) => focusable.get((position + focusable.len() - 1) % focusable.len()),
+            None => focusable.last(),
+        };
+        self.set_focus(previous.copied())
+    }
+
+    /// Indices of the components that can receive focus, in focus order.
+    fn focusable_components(&mut self) -> Vec<usize> {
+        self.layout
+            .components_mut()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (_, component))| component.is_focusable())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The name of the focused component in the layout.
+    fn focused_name(&mut self) -> Option<String> {
+        let focused = self.focused?;
+        let components = self.layout.named_components_mut();
+        components.get(focused).map(|(name, _, _)| name.to_string())
+    }
+
+    /// The index of the component `name` in the layout.
+    fn position_of(&mut self, name: &str) -> Option<usize> {
+        self.layout
+            .named_components_mut()
+            .iter()
+            .position(|(component, _, _)| *component == name)
+    }
+
+    fn focused_position(&self, focusable: &[usize]) -> Option<usize> {
+        let focused = self.focused?;
+        focusable.iter().position(|&index| index == focused)
+    }
+
+    fn set_focus(&mut self, index: Option<usize>) -> Result<()> {
+        if self.focused == index {
+            return Ok(());
+        }
+        if let Some(component) = self.focused_component_mut() {
+            component.blur()?;
+        }
+        self.focused = index;
+        if let Some(component) = self.focused_component_mut() {
+            component.focus()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use ratatui::layout::Constraint;
+
+    use super::*;
+    use crate::{config::ConfigSources, layout::LayoutConfig};
+
+    fn init(screen: &mut Screen, config: &Config) -> Result<()> {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        screen.init(tx, config, Rect::new(0, 0, 40, 8))
+    }
+
+    fn index(screen: &mut Screen, name: &str) -> usize {
+        screen.position_of(name).unwrap()
+    }
+
+    #[test]
+    fn test_state_round_trip() -> Result<()> {
+        let config = Config::from_sources(&ConfigSources::defaults_only())?;
+        let mut screen = Screen::new(Mode::Home, &config)?;
+        init(&mut screen, &config)?;
+        let home = index(&mut screen, "home");
+        let state = screen.save_state();
+        assert_eq!(state.focused.as_deref(), Some("home"));
+        assert_eq!(state.components["home"], json!({ "visits": 1 }));
+
+        let mut restored = Screen::new(Mode::Home, &config)?;
+        restored.restore_state(&state);
+        init(&mut restored, &config)?;
+        assert_eq!(restored.focused, Some(home));
+        assert_eq!(
+            restored.save_state().components["home"],
+            json!({ "visits": 2 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_invalid_state() -> Result<()> {
+        let config = Config::from_sources(&ConfigSources::defaults_only())?;
+        let mut screen = Screen::new(Mode::Home, &config)?;
+        let home = index(&mut screen, "home");
+        screen.restore_state(&ScreenState {
+            mode: Mode::Home,
+            focused: Some("fps".to_string()),
+            components: [("home".to_string(), json!("invalid"))].into(),
+        });
+        init(&mut screen, &config)?;
+        // the counter cannot be focused, and the state of `home` starts over
+        assert_eq!(screen.focused, Some(home));
+        assert_eq!(
+            screen.save_state().components["home"],
+            json!({ "visits": 1 })
+        );
+        Ok(())
+    }
+
+    /// A screen with a component for each name, side by side in that order. `fps` is a frame
+    /// counter that cannot be focused, the other names are focusable.
+    fn side_by_side(names: &[&str]) -> Result<Screen> {
+        let layout = LayoutConfig::Split {
+            direction: Direction::Horizontal,
+            constraints: vec![Constraint::Fill(1); names.len()],
+            children: names
+                .iter()
+                .map(|name| LayoutConfig::Component(name.to_string()))
+                .collect(),
+        };
+        let components = names
+            .iter()
+            .map(|&name| {
+                let component: Box<dyn Component> = match name {
+                    "fps" => Box::new(FpsCounter::default()),
+                    _ => Box::new(Home::new()),
+                };
+                (name.to_string(), component)
+            })
+            .collect();
+        Ok(Screen {
+            mode: Mode::Home,
+            layout: LayoutNode::build(&layout, components)?,
+            focused: None,
+        })
+    }
+
+    #[test]
+    fn test_focus_skips_components_that_cannot_be_focused() -> Result<()> {
+        let mut screen = side_by_side(&["fps", "left", "right"])?;
+        init(&mut screen, &Config::default())?;
+        let [left, right] = [index(&mut screen, "left"), index(&mut screen, "right")];
+        assert_eq!(screen.focused, Some(left));
+        screen.focus_next()?;
+        assert_eq!(screen.focused, Some(right));
+        screen.focus_next()?;
+        assert_eq!(screen.focused, Some(left));
+        screen.focus_previous()?;
+        assert_eq!(screen.focused, Some(right));
+        Ok(())
+    }
+
+    #[test]
+    fn test_focus_follows_the_component_when_the_layout_changes() -> Result<()> {
+        let config = Config::default();
+        let mut screen = side_by_side(&["left", "right"])?;
+        init(&mut screen, &config)?;
+        screen.focus_next()?;
+        let state = screen.save_state();
+        assert_eq!(state.focused.as_deref(), Some("right"));
+
+        let mut restored = side_by_side(&["right", "left"])?;
+        let right = index(&mut restored, "right");
+        restored.restore_state(&state);
+        init(&mut restored, &config)?;
+        assert_eq!(restored.focused, Some(right));
+        Ok(())
+    }
+
+    #[test]
+    fn test_relayout_keeps_the_components() -> Result<()> {
+        let mut config = Config::from_sources(&ConfigSources::defaults_only())?;
+        let mut screen = Screen::new(Mode::Home, &config)?;
+        init(&mut screen, &config)?;
+        config.layouts.insert(
+            Mode::Home,
+            LayoutConfig::Split {
+                direction: Direction::Vertical,
+                constraints: vec![Constraint::Fill(1), Constraint::Length(1)],
+                children: vec![
+                    LayoutConfig::Component("home".to_string()),
+                    LayoutConfig::Component("fps".to_string()),
+                ],
+            },
+        );
+        screen.relayout(&config, Rect::new(0, 0, 40, 8))?;
+        let areas: Vec<_> = screen
+            .layout
+            .named_components_mut()
+            .into_iter()
+            .map(|(name, area, _)| (name.to_string(), area))
+            .collect();
+        assert_eq!(
+            areas,
+            [
+                ("home".to_string(), Rect::new(0, 0, 40, 7)),
+                ("fps".to_string(), Rect::new(0, 7, 40, 1)),
+            ]
+        );
+        assert_eq!(screen.focused, Some(0));
+        assert_eq!(
+            screen.save_state().components["home"],
+            json!({ "visits": 1 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_relayout_rejects_a_layout_without_every_component() -> Result<()> {
+        let mut config = Config::from_sources(&ConfigSources::defaults_only())?;
+        let mut screen = Screen::new(Mode::Home, &config)?;
+        for layout in ["home", "clock"] {
+            config
+                .layouts
+                .insert(Mode::Home, LayoutConfig::Component(layout.to_string()));
+            assert!(screen.relayout(&config, Rect::new(0, 0, 40, 8)).is_err());
+        }
+        assert_eq!(index(&mut screen, "fps"), 0);
+        assert_eq!(index(&mut screen, "home"), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{app::Mode, config::get_data_dir};
+
+/// Bumped whenever the layout of [`State`] changes. Files with another version are ignored.
+pub const STATE_VERSION: u32 = 2;
+
+const STATE_FILE: &str = "state.json";
+
+/// Where the state of the application is kept between runs.
+pub fn state_file() -> PathBuf {
+    get_data_dir().join(STATE_FILE)
+}
+
+/// What the application remembers across restarts: the stack of screens and the state of the
+/// components that opted in through [`crate::components::Component::save_state`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    pub version: u32,
+    /// From the bottom to the top of the stack.
+    pub screens: Vec<ScreenState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScreenState {
+    pub mode: Mode,
+    /// The name of the focused component in the layout.
+    #[serde(default)]
+    pub focused: Option<String>,
+    /// By the name of the component in the layout.
+    #[serde(default)]
+    pub components: BTreeMap<String, serde_json::Value>,
+}
+
+impl State {
+    pub fn new(screens: Vec<ScreenState>) -> Self {
+        Self {
+            version: STATE_VERSION,
+            screens,
+        }
+    }
+
+    /// Reads the state saved by a previous run.
+    ///
+    /// Returns `None` when there is nothing usable to restore. A file that cannot be read is moved
+    /// aside to `state.json.corrupt` with a warning, so that the application still starts.
+    pub fn load(path: &Path) -> Option<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Failed to read the state from {}: {err}", path.display());
+                return None;
+            }
+        };
+        match Self::parse(&text) {
+            Ok(state) if state.version == STATE_VERSION => Some(state),
+            Ok(state) => {
+                warn!(
+                    "Ignoring the state in {}: version {} is not supported",
+                    path.display(),
+                    state.version
+                );
+                None
+            }
+            Err(err) => {
+                let corrupt = path.with_extension("json.corrupt");
+                warn!(
+                    "The state in {} is corrupted and was moved to {}: {err}",
+                    path.display(),
+                    corrupt.display()
+                );
+                if let Err(err) = fs::rename(path, &corrupt) {
+                    warn!("Failed to move the corrupted state: {err}");
+                }
+                None
+            }
+        }
+    }
+
+    /// The version is checked before the rest of the file, so that a state written by a newer
+    /// version is ignored instead of reported as corrupted.
+    fn parse(text: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(text)?;
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| eyre!("Missing version"))?;
+        if version != u64::from(STATE_VERSION) {
+            return Ok(Self {
+                version: u32::try_from(version).unwrap_or(u32::MAX),
+                screens: Vec::new(),
+            });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Writes the state to a temporary file next to `path` and then renames it over `path`, so
+    /// that a crash while saving never leaves a truncated file behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let directory = path
+            .parent()
+            .ok_or_else(|| eyre!("{} has no parent directory", path.display()))?;
+        fs::create_dir_all(directory)?;
+        let temporary = path.with_extension("json.tmp");
+        let mut file = File::create(&temporary)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn temp_state_file(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("state-{name}-{}", std::process::id()))
+            .join(STATE_FILE)
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let path = temp_state_file("round-trip");
+        assert_eq!(State::load(&path), None);
+        let state = State::new(vec![ScreenState {
+            mode: Mode::Home,
+            focused: Some("home".to_string()),
+            components: BTreeMap::from([("home".to_string(), json!({ "scroll": 3 }))]),
+        }]);
+        state.save(&path)?;
+        assert_eq!(State::load(&path), Some(state));
+        assert!(!path.with_extension("json.tmp").exists());
+        fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_file_is_moved_aside() -> Result<()> {
+        let path = temp_state_file("corrupted");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, r#"{ "version": 2, "screens": [{ "mode": "#)?;
+        assert_eq!(State::load(&path), None);
+        assert!(!path.exists());
+        assert!(path.with_extension("json.corrupt").exists());
+        fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_version_is_ignored() -> Result<()> {
+        let path = temp_state_file("version");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, r#"{ "version": 99, "layout": "unknown" }"#)?;
+        assert_eq!(State::load(&path), None);
+        // the file is kept for the version that wrote it
+        assert!(path.exists());
+        fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+}
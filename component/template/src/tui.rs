@@ -3,6 +3,7 @@
 use std::{
     io::{stdout, Stdout},
     ops::{Deref, DerefMut},
+    sync::Arc,
     time::Duration,
 };
 
@@ -11,22 +12,39 @@ use crossterm::{
     cursor,
     event::{
         DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event as CrosstermEvent, EventStream, KeyEvent, KeyEventKind, MouseEvent,
+        Event as CrosstermEvent, EventStream, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        MouseEvent, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
     },
+    style::Print,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
-use futures::{FutureExt, StreamExt};
-use ratatui::backend::CrosstermBackend as Backend;
+use futures::{future, stream::BoxStream, FutureExt, Stream, StreamExt};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Position, Rect},
+    text::Text,
+    widgets::{Paragraph, Widget},
+    TerminalOptions, Viewport,
+};
 use serde::{Deserialize, Serialize};
 use tokio::{
-    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        Mutex, Notify,
+    },
     task::JoinHandle,
-    time::interval,
+    time::{interval, sleep_until, Instant},
 };
 use tokio_util::sync::CancellationToken;
-use tracing::error;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    background::Background,
+    color_depth::ColorDepth,
+    recording::{self, RecordedEvent},
+};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Event {
     Init,
     Quit,
@@ -42,24 +60,84 @@ pub enum Event {
     Resize(u16, u16),
 }
 
-pub struct Tui {
-    pub terminal: ratatui::Terminal<Backend<Stdout>>,
-    pub task: JoinHandle<()>,
+/// Where the events of the application come from, besides ticks and renders.
+enum EventSource {
+    /// The terminal, through crossterm's `EventStream`.
+    Terminal,
+    /// Events injected with [`Tui::with_backend`]. The stream is shared so that the event loop
+    /// can be restarted, e.g. after suspending.
+    Stream(Arc<Mutex<BoxStream<'static, Event>>>),
+}
+
+pub struct Tui<B: Backend = CrosstermBackend<Stdout>> {
+    pub terminal: ratatui::Terminal<B>,
+    pub task: Option<JoinHandle<()>>,
     pub cancellation_token: CancellationToken,
     pub event_rx: UnboundedReceiver<Event>,
     pub event_tx: UnboundedSender<Event>,
+    /// The maximum number of frames per second. Frames are only drawn when they are requested
+    /// with [`Tui::request_render`].
     pub frame_rate: f64,
     pub tick_rate: f64,
     pub mouse: bool,
     pub paste: bool,
+    /// Where the application is drawn: the whole screen (in the alternate screen), a number of
+    /// lines below the shell prompt, or a fixed area.
+    pub viewport: Viewport,
+    /// Whether to use the kitty keyboard protocol if the terminal supports it.
+    pub keyboard_enhancement: bool,
+    /// Whether the terminal supports the kitty keyboard protocol, once it was asked.
+    keyboard_enhancement_supported: Option<bool>,
+    /// Whether the keyboard enhancement flags are pushed, so that releases and repeats are sent.
+    keyboard_enhanced: bool,
+    /// The background color of the terminal, detected when the terminal is first entered. `None`
+    /// if the terminal did not report it.
+    pub background: Option<Background>,
+    /// How long to wait for the terminal to report its background color.
+    pub background_timeout: Duration,
+    background_queried: bool,
+    /// How many colors the terminal can display. Colors are downsampled to it when drawing.
+    pub color_depth: ColorDepth,
+    /// Events to send before the events of the terminal, for `--replay`.
+    pub replay: Option<Vec<RecordedEvent>>,
+    events: EventSource,
+    /// Wakes the event loop to send an [`Event::Render`], see [`Tui::request_render`].
+    render_requests: Arc<Notify>,
+    /// Creates a backend that draws to the terminal on stdout. `None` if the backend draws
+    /// somewhere else, in which case the terminal is left alone: it is not switched to raw mode or
+    /// the alternate screen, and it is not queried.
+    terminal_backend: Option<fn() -> B>,
 }
 
 impl Tui {
+    /// A `Tui` that draws to the terminal and reads its events from it.
     pub fn new() -> Result<Self> {
+        let terminal = ratatui::Terminal::new(CrosstermBackend::new(stdout()))?;
+        let mut tui = Self::build(terminal, EventSource::Terminal);
+        tui.color_depth = ColorDepth::detect();
+        tui.terminal_backend = Some(|| CrosstermBackend::new(stdout()));
+        Ok(tui)
+    }
+}
+
+impl<B: Backend> Tui<B> {
+    /// A `Tui` that draws to `backend` and reads its events from `events` instead of the
+    /// terminal, e.g. to run the application against a `TestBackend` with scripted input. The
+    /// application quits when `events` ends.
+    pub fn with_backend(
+        backend: B,
+        events: impl Stream<Item = Event> + Send + 'static,
+    ) -> Result<Self> {
+        let terminal = ratatui::Terminal::new(backend)?;
+        let events = EventSource::Stream(Arc::new(Mutex::new(events.boxed())));
+        Ok(Self::build(terminal, events))
+    }
+
+    fn build(terminal: ratatui::Terminal<B>, events: EventSource) -> Self {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
-        Ok(Self {
-            terminal: ratatui::Terminal::new(Backend::new(stdout()))?,
-            task: tokio::spawn(async {}),
+        Self {
+            terminal,
+            task: None,
             cancellation_token: CancellationToken::new(),
             event_rx,
             event_tx,
@@ -67,7 +145,19 @@ impl Tui {
             tick_rate: 4.0,
             mouse: false,
             paste: false,
-        })
+            viewport: Viewport::Fullscreen,
+            keyboard_enhancement: false,
+            keyboard_enhancement_supported: None,
+            keyboard_enhanced: false,
+            background: None,
+            background_timeout: Duration::from_millis(100),
+            background_queried: false,
+            color_depth: ColorDepth::TrueColor,
+            replay: None,
+            events,
+            render_requests: Arc::new(Notify::new()),
+            terminal_backend: None,
+        }
     }
 
     pub fn tick_rate(mut self, tick_rate: f64) -> Self {
@@ -90,71 +180,64 @@ impl Tui {
         self
     }
 
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn keyboard_enhancement(mut self, keyboard_enhancement: bool) -> Self {
+        self.keyboard_enhancement = keyboard_enhancement;
+        self
+    }
+
+    pub fn background_timeout(mut self, background_timeout: Duration) -> Self {
+        self.background_timeout = background_timeout;
+        self
+    }
+
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.color_depth = color_depth;
+        self
+    }
+
+    /// Replays recorded events when the terminal is first entered. Ticks and renders come from the
+    /// recording too, so the application goes through the same states as when it was recorded.
+    pub fn replay(mut self, events: Vec<RecordedEvent>) -> Self {
+        self.replay = Some(events);
+        self
+    }
+
     pub fn start(&mut self) {
         self.cancel(); // Cancel any existing task
         self.cancellation_token = CancellationToken::new();
-        let event_loop = Self::event_loop(
+        let event_loop = event_loop(
             self.event_tx.clone(),
             self.cancellation_token.clone(),
             self.tick_rate,
             self.frame_rate,
+            self.replay.take(),
+            self.render_requests.clone(),
+            match &self.events {
+                EventSource::Terminal => terminal_events(self.keyboard_enhanced),
+                EventSource::Stream(stream) => stream.clone(),
+            },
         );
-        self.task = tokio::spawn(async {
+        self.task = Some(tokio::spawn(async {
             event_loop.await;
-        });
-    }
-
-    async fn event_loop(
-        event_tx: UnboundedSender<Event>,
-        cancellation_token: CancellationToken,
-        tick_rate: f64,
-        frame_rate: f64,
-    ) {
-        let mut event_stream = EventStream::new();
-        let mut tick_interval = interval(Duration::from_secs_f64(1.0 / tick_rate));
-        let mut render_interval = interval(Duration::from_secs_f64(1.0 / frame_rate));
-
-        // if this fails, then it's likely a bug in the calling code
-        event_tx
-            .send(Event::Init)
-            .expect("failed to send init event");
-        loop {
-            let event = tokio::select! {
-                _ = cancellation_token.cancelled() => {
-                    break;
-                }
-                _ = tick_interval.tick() => Event::Tick,
-                _ = render_interval.tick() => Event::Render,
-                crossterm_event = event_stream.next().fuse() => match crossterm_event {
-                    Some(Ok(event)) => match event {
-                        CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => Event::Key(key),
-                        CrosstermEvent::Mouse(mouse) => Event::Mouse(mouse),
-                        CrosstermEvent::Resize(x, y) => Event::Resize(x, y),
-                        CrosstermEvent::FocusLost => Event::FocusLost,
-                        CrosstermEvent::FocusGained => Event::FocusGained,
-                        CrosstermEvent::Paste(s) => Event::Paste(s),
-                        _ => continue, // ignore other events
-                    }
-                    Some(Err(_)) => Event::Error,
-                    None => break, // the event stream has stopped and will not produce any more events
-                },
-            };
-            if event_tx.send(event).is_err() {
-                // the receiver has been dropped, so there's no point in continuing the loop
-                break;
-            }
-        }
-        cancellation_token.cancel();
+        }));
     }
 
     pub fn stop(&self) -> Result<()> {
         self.cancel();
+        let Some(task) = &self.task else {
+            return Ok(());
+        };
         let mut counter = 0;
-        while !self.task.is_finished() {
+        while !task.is_finished() {
             std::thread::sleep(Duration::from_millis(1));
             counter += 1;
             if counter > 50 {
-                self.task.abort();
+                task.abort();
             }
             if counter > 100 {
                 error!("Failed to abort task in 100 milliseconds for unknown reason");
@@ -165,21 +248,95 @@ impl Tui {
     }
 
     pub fn enter(&mut self) -> Result<()> {
+        if let Some(terminal_backend) = self.terminal_backend {
+            self.enter_terminal(terminal_backend)?;
+        }
+        self.start();
+        Ok(())
+    }
+
+    fn enter_terminal(&mut self, terminal_backend: fn() -> B) -> Result<()> {
         crossterm::terminal::enable_raw_mode()?;
-        crossterm::execute!(stdout(), EnterAlternateScreen, cursor::Hide)?;
+        // The response has to be read before the event stream starts reading the input.
+        if !self.background_queried {
+            self.background_queried = true;
+            self.background = self.query_background();
+            debug!("Terminal background: {:?}", self.background);
+        }
+        if self.viewport == Viewport::Fullscreen {
+            crossterm::execute!(stdout(), EnterAlternateScreen)?;
+        } else {
+            // An inline viewport is placed at the cursor, which moves when the application is
+            // suspended, so the terminal is created again every time it is entered.
+            self.terminal = ratatui::Terminal::with_options(
+                terminal_backend(),
+                TerminalOptions {
+                    viewport: self.viewport.clone(),
+                },
+            )?;
+        }
+        crossterm::execute!(stdout(), cursor::Hide)?;
+        if self.keyboard_enhancement && self.supports_keyboard_enhancement() {
+            crossterm::execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                )
+            )?;
+            self.keyboard_enhanced = true;
+        }
         if self.mouse {
             crossterm::execute!(stdout(), EnableMouseCapture)?;
         }
         if self.paste {
             crossterm::execute!(stdout(), EnableBracketedPaste)?;
         }
-        self.start();
         Ok(())
     }
 
+    /// Asks the terminal whether it supports the kitty keyboard protocol the first time, so that
+    /// terminals without it fall back to the legacy encoding.
+    fn supports_keyboard_enhancement(&mut self) -> bool {
+        use std::io::IsTerminal;
+
+        *self.keyboard_enhancement_supported.get_or_insert_with(|| {
+            let supported = stdout().is_terminal()
+                && crossterm::terminal::supports_keyboard_enhancement()
+                    .inspect_err(|err| warn!("Failed to query keyboard enhancement: {err}"))
+                    .unwrap_or(false);
+            debug!("Keyboard enhancement supported: {supported}");
+            supported
+        })
+    }
+
+    /// Asks the terminal for its background color, or returns `None` if stdin or stdout is not a
+    /// terminal, or if the terminal does not answer within the timeout.
+    fn query_background(&self) -> Option<Background> {
+        #[cfg(unix)]
+        {
+            use std::io::{stdin, IsTerminal};
+            use std::os::fd::AsFd;
+
+            if !stdin().is_terminal() || !stdout().is_terminal() {
+                return None;
+            }
+            crate::background::query_background(
+                stdin().as_fd(),
+                &mut stdout(),
+                self.background_timeout,
+            )
+            .inspect_err(|err| warn!("Failed to query the terminal background: {err}"))
+            .ok()
+            .flatten()
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
     pub fn exit(&mut self) -> Result<()> {
         self.stop()?;
-        if crossterm::terminal::is_raw_mode_enabled()? {
+        if self.terminal_backend.is_some() && crossterm::terminal::is_raw_mode_enabled()? {
             self.flush()?;
             if self.paste {
                 crossterm::execute!(stdout(), DisableBracketedPaste)?;
@@ -187,12 +344,54 @@ impl Tui {
             if self.mouse {
                 crossterm::execute!(stdout(), DisableMouseCapture)?;
             }
-            crossterm::execute!(stdout(), LeaveAlternateScreen, cursor::Show)?;
+            if self.keyboard_enhanced {
+                crossterm::execute!(stdout(), PopKeyboardEnhancementFlags)?;
+                self.keyboard_enhanced = false;
+            }
+            if self.viewport == Viewport::Fullscreen {
+                crossterm::execute!(stdout(), LeaveAlternateScreen)?;
+            } else {
+                // leave what was drawn last in the scrollback and continue below it
+                let area = self.viewport_area();
+                self.terminal
+                    .set_cursor_position(Position::new(0, area.bottom().saturating_sub(1)))?;
+                crossterm::execute!(stdout(), Print("\r\n"))?;
+            }
+            crossterm::execute!(stdout(), cursor::Show)?;
             crossterm::terminal::disable_raw_mode()?;
         }
         Ok(())
     }
 
+    /// The area that the application is drawn in.
+    pub fn viewport_area(&mut self) -> Rect {
+        self.terminal.get_frame().area()
+    }
+
+    /// Adapts the viewport to a new size of the terminal and returns its area. A fixed viewport
+    /// keeps its area.
+    pub fn resize_viewport(&mut self, width: u16, height: u16) -> Result<Rect> {
+        if !matches!(self.viewport, Viewport::Fixed(_)) {
+            self.terminal.resize(Rect::new(0, 0, width, height))?;
+        }
+        Ok(self.viewport_area())
+    }
+
+    /// Prints `text` above an inline viewport, where it stays in the scrollback when the
+    /// application exits. The other viewports have nothing above them, so the text is logged.
+    pub fn print_above(&mut self, text: &str) -> Result<()> {
+        if !matches!(self.viewport, Viewport::Inline(_)) {
+            info!("{text}");
+            return Ok(());
+        }
+        let text = Text::raw(text);
+        let height = u16::try_from(text.height()).unwrap_or(u16::MAX);
+        self.terminal.insert_before(height, |buffer| {
+            Paragraph::new(text).render(buffer.area, buffer);
+        })?;
+        Ok(())
+    }
+
     pub fn cancel(&self) {
         self.cancellation_token.cancel();
     }
@@ -200,7 +399,9 @@ impl Tui {
     pub fn suspend(&mut self) -> Result<()> {
         self.exit()?;
         #[cfg(not(windows))]
-        signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?;
+        if self.terminal_backend.is_some() {
+            signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?;
+        }
         Ok(())
     }
 
@@ -209,27 +410,210 @@ impl Tui {
         Ok(())
     }
 
+    /// Asks for an [`Event::Render`]. It is sent right away if no frame was sent during the last
+    /// `1 / frame_rate` seconds, and at the end of that period otherwise. Requests that are made
+    /// before the render is sent are merged into it.
+    pub fn request_render(&self) {
+        self.render_requests.notify_one();
+    }
+
     pub async fn next_event(&mut self) -> Option<Event> {
         self.event_rx.recv().await
     }
 }
 
-impl Deref for Tui {
-    type Target = ratatui::Terminal<Backend<Stdout>>;
+impl<B: Backend> Deref for Tui<B> {
+    type Target = ratatui::Terminal<B>;
 
     fn deref(&self) -> &Self::Target {
         &self.terminal
     }
 }
 
-impl DerefMut for Tui {
+impl<B: Backend> DerefMut for Tui<B> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.terminal
     }
 }
 
-impl Drop for Tui {
+impl<B: Backend> Drop for Tui<B> {
     fn drop(&mut self) {
         self.exit().unwrap();
     }
 }
+
+/// Sends the events to the application until it is cancelled: ticks at their rate, renders when
+/// they are requested through `render_requests` but at most at `frame_rate`, and the events of
+/// `events`, after the `replay` if there is one.
+async fn event_loop(
+    event_tx: UnboundedSender<Event>,
+    cancellation_token: CancellationToken,
+    tick_rate: f64,
+    frame_rate: f64,
+    replay: Option<Vec<RecordedEvent>>,
+    render_requests: Arc<Notify>,
+    events: Arc<Mutex<BoxStream<'static, Event>>>,
+) {
+    let mut events = events.lock().await;
+
+    // if this fails, then it's likely a bug in the calling code
+    event_tx
+        .send(Event::Init)
+        .expect("failed to send init event");
+    if let Some(events) = replay {
+        // the init event was already sent
+        let events = events
+            .into_iter()
+            .filter(|recorded| recorded.event != Event::Init)
+            .collect();
+        if !recording::replay(events, &event_tx, &cancellation_token).await {
+            return;
+        }
+        debug!("Replay finished, reading events from the terminal");
+    }
+    // the interval starts after the replay so that it does not catch up on missed ticks
+    let mut tick_interval = interval(Duration::from_secs_f64(1.0 / tick_rate));
+    let frame_duration = Duration::from_secs_f64(1.0 / frame_rate);
+    let mut last_render: Option<Instant> = None;
+    let render_deadline = sleep_until(Instant::now());
+    tokio::pin!(render_deadline);
+    let mut render_pending = false;
+    loop {
+        let event = tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                break;
+            }
+            _ = tick_interval.tick() => Event::Tick,
+            _ = render_requests.notified() => {
+                // a request while a render is pending is merged into it
+                if !render_pending {
+                    render_pending = true;
+                    let deadline =
+                        last_render.map_or_else(Instant::now, |last| last + frame_duration);
+                    render_deadline.as_mut().reset(deadline);
+                }
+                continue;
+            }
+            _ = &mut render_deadline, if render_pending => {
+                render_pending = false;
+                last_render = Some(Instant::now());
+                Event::Render
+            }
+            event = events.next().fuse() => match event {
+                Some(event) => event,
+                None => {
+                    // the event stream has stopped, so there is no input left to wait for
+                    let _ = event_tx.send(Event::Quit);
+                    break;
+                }
+            },
+        };
+        if event_tx.send(event).is_err() {
+            // the receiver has been dropped, so there's no point in continuing the loop
+            break;
+        }
+    }
+    cancellation_token.cancel();
+}
+
+/// The events of the terminal. Key releases and repeats are only reported as such with the
+/// keyboard enhancement, and are dropped otherwise like other terminals do.
+fn terminal_events(keyboard_enhanced: bool) -> Arc<Mutex<BoxStream<'static, Event>>> {
+    let events = EventStream::new().filter_map(move |event| {
+        future::ready(match event {
+            Ok(CrosstermEvent::Key(key))
+                if key.kind == KeyEventKind::Press || keyboard_enhanced =>
+            {
+                Some(Event::Key(key))
+            }
+            Ok(CrosstermEvent::Mouse(mouse)) => Some(Event::Mouse(mouse)),
+            Ok(CrosstermEvent::Resize(x, y)) => Some(Event::Resize(x, y)),
+            Ok(CrosstermEvent::FocusLost) => Some(Event::FocusLost),
+            Ok(CrosstermEvent::FocusGained) => Some(Event::FocusGained),
+            Ok(CrosstermEvent::Paste(s)) => Some(Event::Paste(s)),
+            Ok(_) => None, // ignore other events
+            Err(_) => Some(Event::Error),
+        })
+    });
+    Arc::new(Mutex::new(events.boxed()))
+}
+
+/// Parses the `--viewport` argument: `fullscreen`, `inline:<HEIGHT>` or
+/// `fixed:<X>,<Y>,<WIDTH>,<HEIGHT>`.
+pub fn parse_viewport(raw: &str) -> Result<Viewport, String> {
+    let error = || {
+        format!("Unable to parse viewport `{raw}`, expected `fullscreen`, `inline:<HEIGHT>` or `fixed:<X>,<Y>,<WIDTH>,<HEIGHT>`")
+    };
+    let number = |value: &str| value.trim().parse::<u16>().map_err(|_| error());
+    let (kind, value) = raw.split_once(':').unwrap_or((raw, ""));
+    match kind.trim().to_ascii_lowercase().as_str() {
+        "fullscreen" if value.is_empty() => Ok(Viewport::Fullscreen),
+        "inline" => Ok(Viewport::Inline(number(value)?)),
+        "fixed" => {
+            let values = value
+                .split(',')
+                .map(number)
+                .collect::<Result<Vec<_>, _>>()?;
+            let [x, y, width, height] = values[..] else {
+                return Err(error());
+            };
+            Ok(Viewport::Fixed(Rect::new(x, y, width, height)))
+        }
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_viewport() {
+        assert_eq!(parse_viewport("fullscreen"), Ok(Viewport::Fullscreen));
+        assert_eq!(parse_viewport("inline:8"), Ok(Viewport::Inline(8)));
+        assert_eq!(
+            parse_viewport("Fixed: 1, 2, 40, 10"),
+            Ok(Viewport::Fixed(Rect::new(1, 2, 40, 10)))
+        );
+        assert!(parse_viewport("inline").is_err());
+        assert!(parse_viewport("fixed:1,2,3").is_err());
+        assert!(parse_viewport("fullscreen:1").is_err());
+        assert!(parse_viewport("window").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_renders_when_requested_at_most_at_the_frame_rate() {
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let cancellation_token = CancellationToken::new();
+        let render_requests = Arc::new(Notify::new());
+        let events = Arc::new(Mutex::new(futures::stream::pending().boxed()));
+        // one tick right away and none after it
+        let task = tokio::spawn(event_loop(
+            event_tx,
+            cancellation_token.clone(),
+            0.001,
+            20.0,
+            None,
+            render_requests.clone(),
+            events,
+        ));
+        assert_eq!(event_rx.recv().await, Some(Event::Init));
+        assert_eq!(event_rx.recv().await, Some(Event::Tick));
+
+        render_requests.notify_one();
+        render_requests.notify_one();
+        assert_eq!(event_rx.recv().await, Some(Event::Render));
+        let first = Instant::now();
+        render_requests.notify_one();
+        assert_eq!(event_rx.recv().await, Some(Event::Render));
+        assert!(first.elapsed() >= Duration::from_millis(45));
+        // the two requests before the first render were merged into it
+        let nothing = tokio::time::timeout(Duration::from_millis(100), event_rx.recv()).await;
+        assert!(nothing.is_err(), "{nothing:?}");
+
+        cancellation_token.cancel();
+        task.await.unwrap();
+    }
+}
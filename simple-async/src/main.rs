@@ -12,6 +12,8 @@ use crate::{
 pub mod app;
 pub mod event;
 pub mod handler;
+#[cfg(test)]
+mod snapshot;
 pub mod tui;
 pub mod ui;
 
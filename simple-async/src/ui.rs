@@ -32,3 +32,16 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         frame.area(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    #[test]
+    fn test_render() {
+        let mut app = App::new();
+        app.counter = 3;
+        assert_snapshot("ui", 80, 8, |frame| render(&mut app, frame));
+    }
+}
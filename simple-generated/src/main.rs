@@ -1,6 +1,8 @@
 pub use app::App;
 
 pub mod app;
+#[cfg(test)]
+mod snapshot;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
@@ -80,3 +80,15 @@ impl App {
         self.running = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    #[test]
+    fn test_draw() {
+        let mut app = App::new();
+        assert_snapshot("app", 60, 8, |frame| app.draw(frame));
+    }
+}